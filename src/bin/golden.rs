@@ -0,0 +1,44 @@
+// src/bin/golden.rs
+// Guards against silent numerical regressions: re-runs the golden cases in verify.rs and compares
+// them with the snapshots in goldens/. Pass --regenerate after an intentional change of the numerics.
+use n_pendulum_sim::verify;
+use std::{env, path::Path, process};
+
+const GOLDEN_DIR: &str = "goldens";
+const USAGE: &str = "Usage: golden [--regenerate]";
+
+fn run(args: &[String]) -> Result<bool, String> {
+    let dir = Path::new(GOLDEN_DIR);
+    match args {
+        [] => {
+            let mut all_passed = true;
+            for check in verify::check_goldens(dir)? {
+                let verdict = if check.passed() { "ok" } else { "MISMATCH" };
+                println!("{:<8} max error {:.3e}  {}", check.name, check.max_error, verdict);
+                all_passed &= check.passed();
+            }
+            Ok(all_passed)
+        }
+        [flag] if flag == "--regenerate" => {
+            verify::write_goldens(dir)?;
+            println!("Rewrote {} golden files in {}", verify::GOLDEN_CASES.len(), GOLDEN_DIR);
+            Ok(true)
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(true) => {}
+        Ok(false) => {
+            eprintln!("golden: trajectories differ from the snapshots (tolerance {:e})", verify::GOLDEN_TOLERANCE);
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("golden: {}", e);
+            process::exit(1);
+        }
+    }
+}
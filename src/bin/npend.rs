@@ -0,0 +1,41 @@
+// src/bin/npend.rs
+// Runs one simulation from a JSON config (same shape as the /simulate body) without the web server.
+use n_pendulum_sim::{export, plot, ui};
+use std::{env, fs, path::Path, process};
+
+const USAGE: &str = "Usage: npend <config.json> <out.csv> [out.png]";
+
+fn run(args: &[String]) -> Result<(), String> {
+    let (config_path, csv_path, png_path) = match args {
+        [config, csv] => (config, csv, None),
+        [config, csv, png] => (config, csv, Some(png)),
+        _ => return Err(USAGE.to_string()),
+    };
+
+    let config = fs::read_to_string(config_path)
+        .map_err(|e| format!("Cannot read config '{}': {}", config_path, e))?;
+    let params: ui::SimParams = serde_json::from_str(&config)
+        .map_err(|e| format!("Invalid config '{}': {}", config_path, e))?;
+
+    let run = ui::run_simulation(&params).map_err(|e| e.to_string())?;
+    let n = run.solver.n;
+
+    fs::write(csv_path, export::trajectory_csv(&run.t, &run.positions, n))
+        .map_err(|e| format!("Cannot write '{}': {}", csv_path, e))?;
+    println!("Wrote {} rows to {}", run.t.len(), csv_path);
+
+    if let Some(png_path) = png_path {
+        plot::trajectory_png_file(Path::new(png_path), &run.plot(), run.supersample)
+            .map_err(|e| format!("Cannot write '{}': {}", png_path, e))?;
+        println!("Wrote plot to {}", png_path);
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("npend: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,79 @@
+// src/circular.rs
+
+/// Statistics of angles on the circle (radians). Averaging the raw numbers goes wrong for angles that
+/// wrap: samples clustered around ±π average to about 0, the opposite direction. Each angle is
+/// treated as the unit vector (cos θ, sin θ) instead, and the statistics come from their resultant.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CircularStats {
+    pub mean: Option<f64>, // atan2 of the resultant in (-π, π], None when it vanishes (no preferred direction)
+    pub resultant_length: f64, // |mean unit vector| in [0, 1]: 1 for identical angles, ~0 for uniform ones
+}
+
+impl CircularStats {
+    /// Circular variance 1 - R, from 0 (all angles equal) to 1 (no preferred direction).
+    pub fn variance(&self) -> f64 {
+        1.0 - self.resultant_length
+    }
+}
+
+/// Below this resultant length the mean direction is numerical noise.
+const MIN_RESULTANT: f64 = 1e-12;
+
+/// Circular statistics of `angles` with the given non-negative weights (e.g. trapezoidal time steps).
+/// Empty or zero total weight gives no mean and R = 0.
+pub fn weighted_stats(angles: &[f64], weights: &[f64]) -> CircularStats {
+    let (mut c, mut s, mut total) = (0.0, 0.0, 0.0);
+    for (theta, w) in angles.iter().zip(weights) {
+        c += w * theta.cos();
+        s += w * theta.sin();
+        total += w;
+    }
+    if total <= 0.0 {
+        return CircularStats { mean: None, resultant_length: 0.0 };
+    }
+    let resultant_length = (c.hypot(s) / total).min(1.0);
+    CircularStats {
+        mean: (resultant_length > MIN_RESULTANT).then(|| s.atan2(c)),
+        resultant_length,
+    }
+}
+
+/// Equally weighted circular statistics of `angles`.
+pub fn stats(angles: &[f64]) -> CircularStats {
+    weighted_stats(angles, &vec![1.0; angles.len()])
+}
+
+/// Mean direction of `angles` in (-π, π], None when they cancel out.
+pub fn circular_mean(angles: &[f64]) -> Option<f64> {
+    stats(angles).mean
+}
+
+/// Circular variance 1 - R of `angles`, in [0, 1].
+pub fn circular_variance(angles: &[f64]) -> f64 {
+    stats(angles).variance()
+}
+
+/// Kuramoto order parameter R of `angles`: how tightly they bunch around one direction.
+pub fn order_parameter(angles: &[f64]) -> f64 {
+    stats(angles).resultant_length
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn mean_of_angles_straddling_the_cut() {
+        // π ± 0.05 and π ± 0.1, stored wrapped to (-π, π] so they sit on both sides of the cut: the
+        // arithmetic mean (~0) points the opposite way, the circular one at ±π
+        let angles: Vec<f64> = [0.05, -0.05, 0.1, -0.1].iter().map(|d| if *d > 0.0 { -PI + d } else { PI + d }).collect();
+        assert!((angles.iter().sum::<f64>() / angles.len() as f64).abs() < 0.1);
+        let mean = circular_mean(&angles).expect("the angles do not cancel out");
+        assert!((mean.abs() - PI).abs() < 1e-12, "mean {}", mean);
+        assert!(circular_variance(&angles) < 0.01);
+
+        let uniform: Vec<f64> = (0..8).map(|k| k as f64 * PI / 4.0).collect();
+        assert!((circular_variance(&uniform) - 1.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,130 @@
+// src/export.rs
+use nalgebra::DVector;
+use serde::Serialize;
+
+/// Formats a trajectory as CSV with a `t,x1,y1,x2,y2,...` header and one row per time step.
+pub fn trajectory_csv(t: &[f64], positions: &[Vec<f64>], n: usize) -> String {
+    let mut csv = String::from("t");
+    for k in 1..=n {
+        csv.push_str(&format!(",x{},y{}", k, k));
+    }
+    csv.push('\n');
+
+    for (time, row) in t.iter().zip(positions) {
+        csv.push_str(&time.to_string());
+        for v in row {
+            csv.push(',');
+            csv.push_str(&v.to_string());
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Formats a trajectory as a GeoJSON FeatureCollection with one LineString per bob,
+/// its coordinates being the (x, y) positions in meters and its properties `{ "link_index": k }` (1-based).
+pub fn trajectory_geojson(positions: &[Vec<f64>], n: usize) -> String {
+    let features: Vec<serde_json::Value> = (0..n)
+        .map(|k| {
+            let coordinates: Vec<[f64; 2]> = positions.iter().map(|row| [row[2 * k], row[2 * k + 1]]).collect();
+            serde_json::json!({
+                "type": "Feature",
+                "geometry": { "type": "LineString", "coordinates": coordinates },
+                "properties": { "link_index": k + 1 },
+            })
+        })
+        .collect();
+    serde_json::json!({ "type": "FeatureCollection", "features": features }).to_string()
+}
+
+/// A trajectory as rectangular arrays, each of which `np.array(...)` turns into one ndarray.
+/// The schema is stable; with `steps` samples and `n` links:
+/// * `t`: shape (steps,), time (s)
+/// * `theta`: shape (n, steps), absolute angle of each link from straight down (rad)
+/// * `omega`: shape (n, steps), angular velocity of each link (rad/s)
+/// * `xy`: shape (n, steps, 2), bob positions (m) with y up and the pivot at the origin
+///
+/// Rows are links so that `theta[k]` is one link's time series, ready for `plt.plot(t, theta[k])`.
+#[derive(Serialize, Debug, Clone)]
+pub struct TrajectoryArrays {
+    pub t: Vec<f64>,
+    pub theta: Vec<Vec<f64>>,
+    pub omega: Vec<Vec<f64>>,
+    pub xy: Vec<Vec<[f64; 2]>>,
+}
+
+/// Reshapes a solution [θ1..θn, ω1..ωn] per step and its flattened positions into `TrajectoryArrays`.
+pub fn trajectory_arrays(t: &[f64], sol: &[DVector<f64>], positions: &[Vec<f64>], n: usize) -> TrajectoryArrays {
+    TrajectoryArrays {
+        t: t.to_vec(),
+        theta: (0..n).map(|k| sol.iter().map(|state| state[k]).collect()).collect(),
+        omega: (0..n).map(|k| sol.iter().map(|state| state[n + k]).collect()).collect(),
+        xy: (0..n)
+            .map(|k| positions.iter().map(|row| [row[2 * k], row[2 * k + 1]]).collect())
+            .collect(),
+    }
+}
+
+/// Transposes rectangular `[step][variable]` rows into `[variable][step]` columns, one contiguous
+/// time series per variable. Every row must be as long as the first.
+pub fn transpose(rows: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let width = rows.first().map_or(0, Vec::len);
+    (0..width).map(|j| rows.iter().map(|row| row[j]).collect()).collect()
+}
+
+/// BLAKE3 hex digest of the solution's f64 values in order, little-endian.
+/// Same inputs give the same hash on the same build; a different compiler or platform may
+/// round differently, so don't compare hashes across builds.
+#[cfg(feature = "hash")]
+pub fn trajectory_hash(sol: &[DVector<f64>]) -> String {
+    let mut hasher = blake3::Hasher::new();
+    for state in sol {
+        for v in state.iter() {
+            hasher.update(&v.to_le_bytes());
+        }
+    }
+    hasher.finalize().to_hex().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::NPendulumSolver;
+    use crate::math::STANDARD_GRAVITY;
+
+    #[test]
+    fn trajectory_arrays_follow_the_documented_shapes() {
+        // t is (steps,), theta and omega (n, steps), xy (n, steps, 2), each entry copied from the run
+        let (n, steps) = (3, 101);
+        let solver = NPendulumSolver::new(n, vec![0.0, 1.0, 2.0, 0.5], vec![0.0, 1.0, 0.5, 0.75], STANDARD_GRAVITY);
+        let (t, sol) = solver.solve(vec![0.0, 1.0, -0.5, 2.0], vec![0.0, 0.0, 1.0, 0.0], 2.0, steps).expect("test chain is nonsingular");
+        let positions = solver.cartesian_positions(&t, &sol);
+        let arrays = trajectory_arrays(&t, &sol, &positions, n);
+
+        assert_eq!(arrays.t.len(), steps);
+        assert_eq!((arrays.theta.len(), arrays.omega.len(), arrays.xy.len()), (n, n, n));
+        for k in 0..n {
+            assert_eq!((arrays.theta[k].len(), arrays.omega[k].len(), arrays.xy[k].len()), (steps, steps, steps));
+            for i in 0..steps {
+                assert_eq!(arrays.theta[k][i], sol[i][k]);
+                assert_eq!(arrays.omega[k][i], sol[i][n + k]);
+                assert_eq!(arrays.xy[k][i], [positions[i][2 * k], positions[i][2 * k + 1]]);
+            }
+        }
+    }
+
+    #[test]
+    fn transpose_turns_states_into_series_and_back() {
+        let solver = NPendulumSolver::new(4, vec![0.0, 1.0, 1.0, 1.0, 1.0], vec![0.0, 1.0, 1.0, 1.0, 1.0], STANDARD_GRAVITY);
+        let (_t, sol) = solver.solve(vec![0.0, 1.0, 0.5, -0.5, 0.25], vec![0.0; 5], 1.0, 57).expect("test chain is nonsingular");
+        let rows: Vec<Vec<f64>> = sol.iter().map(|state| state.iter().copied().collect()).collect();
+        let columns = transpose(&rows);
+
+        assert_eq!(columns.len(), 8);
+        for (j, column) in columns.iter().enumerate() {
+            assert_eq!(column.len(), rows.len());
+            assert!(rows.iter().zip(column).all(|(row, &v)| row[j] == v), "column {}", j);
+        }
+        assert_eq!(transpose(&columns), rows);
+    }
+}
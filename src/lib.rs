@@ -0,0 +1,11 @@
+// src/lib.rs
+pub mod circular;
+pub mod export;
+pub mod logic;
+pub mod math;
+pub mod plot;
+pub mod precision;
+#[cfg(feature = "fft")]
+pub mod spectrum;
+pub mod ui;
+pub mod verify;
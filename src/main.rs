@@ -1,18 +1,15 @@
 // src/main.rs
 use actix_files::Files;
 use actix_web::{middleware, web, App, HttpServer};
+use n_pendulum_sim::ui;
 use std::env;
 
-mod logic;
-mod math;
-mod ui;
-
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    // 1. Initialize the logger so Actix can output to the console
-    // "info" means show all info, warnings, and errors.
-    std::env::set_var("RUST_LOG", "actix_web=info");
-    env_logger::init();
+    // 1. Initialize the logger so Actix and the simulation handlers can output to the console
+    // "info" means show all info, warnings, and errors. RUST_LOG overrides this default.
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("actix_web=info,n_pendulum_sim=info"))
+        .init();
 
     let port: u16 = env::var("PORT")
         .unwrap_or_else(|_| "8080".to_string())
@@ -26,6 +23,38 @@ async fn main() -> std::io::Result<()> {
             // 2. Wrap the app in the Logger middleware
             .wrap(middleware::Logger::default())
             .route("/simulate", web::post().to(ui::simulate_handler))
+            .route("/selftest", web::get().to(ui::selftest_handler))
+            .route("/geojson", web::post().to(ui::geojson_handler))
+            .route("/export/csv", web::post().to(ui::csv_handler))
+            .route("/arrays", web::post().to(ui::arrays_handler))
+            .route("/ndjson", web::post().to(ui::ndjson_handler))
+            .route("/pose", web::post().to(ui::pose_handler))
+            .route("/ensemble", web::post().to(ui::ensemble_handler))
+            .route("/sweep", web::post().to(ui::sweep_handler))
+            .route("/bifurcation", web::post().to(ui::bifurcation_handler))
+            .route("/periods", web::post().to(ui::periods_handler))
+            .route("/max_speed", web::post().to(ui::max_speed_handler))
+            .route("/summary", web::get().to(ui::summary_query_handler))
+            .route("/summary", web::post().to(ui::summary_handler))
+            .route("/peak_height", web::post().to(ui::peak_height_handler))
+            .route("/batch", web::post().to(ui::batch_handler))
+            .route("/equilibrium", web::post().to(ui::equilibrium_handler))
+            .route("/state_space", web::post().to(ui::state_space_handler))
+            .route("/potential_landscape", web::post().to(ui::potential_landscape_handler))
+            .route("/from_positions", web::post().to(ui::from_positions_handler))
+            .route("/accelerations", web::post().to(ui::accelerations_handler))
+            .route("/fan", web::post().to(ui::fan_handler))
+            .route("/energy_video", web::post().to(ui::energy_video_handler))
+            .route("/settling", web::post().to(ui::settling_handler))
+            .route("/spectrum", web::post().to(ui::spectrum_handler))
+            .route("/phase", web::post().to(ui::phase_plot_handler))
+            .route("/zero_crossing", web::post().to(ui::zero_crossing_handler))
+            .route("/autocorrelation", web::post().to(ui::autocorrelation_handler))
+            .route("/angle_event", web::post().to(ui::angle_event_handler))
+            .route("/phase_volume", web::post().to(ui::phase_volume_handler))
+            .route("/mass_eigenvalues", web::post().to(ui::mass_eigenvalues_handler))
+            .route("/swept_area", web::post().to(ui::swept_area_handler))
+            .route("/angular_momentum", web::post().to(ui::angular_momentum_handler))
             .service(
                 Files::new("/", "./static")
                     .index_file("index.html")
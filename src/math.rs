@@ -1,38 +1,73 @@
+use crate::precision::Real;
 use nalgebra::{DMatrix, DVector};
 
+/// Standard gravitational acceleration (m/s²), the default `gravity` of a simulation.
+pub const STANDARD_GRAVITY: f64 = 9.81;
+
 /// Solves the Lagrangian equations: M α + C + G = 0
 /// This version preserves 1-based indexing for direct mapping to physics derivations.
-pub struct NPendulumMath {
-    pub g: f64,
+/// Generic over the scalar type so reference runs can use extended precision (see precision.rs).
+pub struct NPendulumMath<T: Real = f64> {
+    pub g: T,
+    pub gravity_angle: T, // Direction of gravity from straight down (rad), 0 for the usual frame
     pub n: usize,
-    pub masses: Vec<f64>,   // [0, m1, m2, ..., mn]
-    pub lengths: Vec<f64>,  // [0, l1, l2, ..., ln]
-    pub angles: Vec<f64>,   // [0, θ1, θ2, ..., θn]
-    pub ang_vels: Vec<f64>, // [0, ω1, ω2, ..., ωn]
+    masses: Vec<T>,       // [0, m1, m2, ..., mn], private: `mass_sums` caches their suffix sums
+    pub lengths: Vec<T>,  // [0, l1, l2, ..., ln]
+    pub length_rates: Vec<T>, // [0, l̇1, l̇2, ..., l̇n] for telescoping links, all zero for fixed ones
+    pub angles: Vec<T>,   // [0, θ1, θ2, ..., θn]
+    pub ang_vels: Vec<T>, // [0, ω1, ω2, ..., ωn]
+    mass_sums: Vec<T>,    // [0, μ1, μ2, ..., μn], μ_k = Σ_{m≥k} m_m
 }
 
-impl NPendulumMath {
-    pub fn new(n: usize, masses: Vec<f64>, lengths: Vec<f64>, angles: Vec<f64>, ang_vels: Vec<f64>) -> Self {
+impl<T: Real> NPendulumMath<T> {
+    /// `g` is the gravitational acceleration (m/s²), usually `STANDARD_GRAVITY`.
+    pub fn new(n: usize, masses: Vec<T>, lengths: Vec<T>, angles: Vec<T>, ang_vels: Vec<T>, g: T) -> Self {
+        // Each sum is folded from k upward, in the order the entries used to sum them on every call
+        let mass_sums = std::iter::once(T::zero())
+            .chain((1..=n).map(|k| masses[k..=n].iter().fold(T::zero(), |acc, &m| acc + m)))
+            .collect();
         Self {
-            g: 9.81,
+            g,
+            gravity_angle: T::zero(),
             n,
             masses,
             lengths,
+            length_rates: vec![T::zero(); n + 1],
             angles,
             ang_vels,
+            mass_sums,
         }
     }
 
-    /// Helper to sum masses from index k to n.
-    fn mass_sum_from(&self, k: usize) -> f64 {
-        self.masses[k..=self.n].iter().sum()
+    /// The padded masses [0, m1, ..., mn]; they are fixed after `new`, build a new `NPendulumMath` to change them.
+    pub fn masses(&self) -> &[T] {
+        &self.masses
+    }
+
+    /// Moves to a new state in place: `angles` = [θ1, ..., θn] and `ang_vels` = [ω1, ..., ωn]
+    /// (without the padding) overwrite the current ones, so repeated evaluations reuse the buffers.
+    pub fn set_state(&mut self, angles: &[T], ang_vels: &[T]) {
+        self.angles[1..=self.n].copy_from_slice(angles);
+        self.ang_vels[1..=self.n].copy_from_slice(ang_vels);
+    }
+
+    /// Sum of the masses from index k to n.
+    fn mass_sum_from(&self, k: usize) -> T {
+        self.mass_sums[k]
     }
 
     /// Computes Mass Matrix M (n x n)
-    pub fn set_mass_matrix(&self) -> DMatrix<f64> {
-        // nalgebra matrices are 0-indexed internally, so M(0,0) corresponds to your M_{1,1}
+    /// Entries only see suffix sums of the masses, so massless interior bobs need no special case;
+    /// `logic::check_mass_pattern` lists the patterns for which M stays invertible.
+    pub fn set_mass_matrix(&self) -> DMatrix<T> {
         let mut m_matrix = DMatrix::zeros(self.n, self.n);
+        self.fill_mass_matrix(&mut m_matrix);
+        m_matrix
+    }
 
+    /// `set_mass_matrix` written into an existing n x n matrix.
+    pub fn fill_mass_matrix(&self, m_matrix: &mut DMatrix<T>) {
+        // nalgebra matrices are 0-indexed internally, so M(0,0) corresponds to your M_{1,1}
         for row in 1..=self.n {
             for col in 1..=self.n {
                 let k = row.max(col);
@@ -46,40 +81,235 @@ impl NPendulumMath {
                 m_matrix[(row - 1, col - 1)] = term;
             }
         }
-        m_matrix
+    }
+
+    /// Checks that |M[i][j] - M[j][i]| < eps for every entry.
+    /// The mass matrix is symmetric by construction, so a failure means `set_mass_matrix` regressed.
+    pub fn is_symmetric(matrix: &DMatrix<T>, eps: f64) -> bool {
+        if !matrix.is_square() {
+            return false;
+        }
+        let size = matrix.nrows();
+        (0..size).all(|i| (i + 1..size).all(|j| (matrix[(i, j)] - matrix[(j, i)]).abs().to_f64() < eps))
     }
 
     /// Computes Centripetal Vector C (n x 1)
-    pub fn set_centripetal_matrix(&self) -> DVector<f64> {
-        let mut c_vec = DVector::zeros(self.n);
+    pub fn set_centripetal_matrix(&self) -> DVector<T> {
+        DVector::from_fn(self.n, |i, _| self.centripetal_term(i + 1))
+    }
 
-        for i in 1..=self.n {
-            let mut f_term = 0.0;
-            for j in 1..=self.n {
-                let m_val = self.mass_sum_from(i.max(j));
-                
-                let term = m_val 
-                    * self.lengths[i] 
-                    * self.lengths[j] 
-                    * (self.angles[i] - self.angles[j]).sin() 
-                    * (self.ang_vels[j] * self.ang_vels[j]);
-                
-                f_term += term;
-            }
-            c_vec[i - 1] = f_term;
+    /// Entry C_i (1-based i) of the centripetal vector.
+    fn centripetal_term(&self, i: usize) -> T {
+        let mut f_term = T::zero();
+        for j in 1..=self.n {
+            let m_val = self.mass_sum_from(i.max(j));
+
+            let term = m_val
+                * self.lengths[i]
+                * self.lengths[j]
+                * (self.angles[i] - self.angles[j]).sin()
+                * (self.ang_vels[j] * self.ang_vels[j]);
+
+            f_term += term;
+        }
+        f_term
+    }
+
+    /// Computes Telescoping Vector D (n x 1), the Coriolis-like coupling between a link's
+    /// extension and the swing: D_i = Σ_j μ_max(i,j) l_i · 2 l̇_j ω_j cos(θ_i - θ_j), μ_k = Σ_{m≥k} m_m.
+    /// With changing lengths the equations become M α + C + D + G = 0 (lengths are linear ramps,
+    /// so there is no l̈ term). Zero when no link telescopes.
+    pub fn set_telescoping_vector(&self) -> DVector<T> {
+        let telescoping = self.is_telescoping();
+        DVector::from_fn(self.n, |i, _| if telescoping { self.telescoping_term(i + 1) } else { T::zero() })
+    }
+
+    /// Whether any link changes length, i.e. whether D can be nonzero.
+    fn is_telescoping(&self) -> bool {
+        self.length_rates.iter().any(|&r| r != T::zero())
+    }
+
+    /// Entry D_i (1-based i) of the telescoping vector.
+    fn telescoping_term(&self, i: usize) -> T {
+        let mut d_term = T::zero();
+        for j in 1..=self.n {
+            let m_val = self.mass_sum_from(i.max(j));
+            d_term += m_val
+                * self.lengths[i]
+                * T::from_f64(2.0)
+                * self.length_rates[j]
+                * self.ang_vels[j]
+                * (self.angles[i] - self.angles[j]).cos();
         }
-        c_vec
+        d_term
     }
 
     /// Computes Gravity Vector G (n x 1)
-    pub fn set_grav_matrix(&self) -> DVector<f64> {
-        let mut g_vec = DVector::zeros(self.n);
+    /// With gravity tilted by φ = `gravity_angle` each term uses sin(θ_i - φ), since only the angle
+    /// between a link and the gravity direction matters.
+    /// With g == 0 (free rotation) this is identically zero and the sines are skipped.
+    /// Without gravity and damping nothing exerts a torque about the pivot, so both the
+    /// total energy and the total angular momentum are conserved.
+    pub fn set_grav_matrix(&self) -> DVector<T> {
+        DVector::from_fn(self.n, |i, _| self.gravity_term(i + 1))
+    }
 
+    /// Entry G_i (1-based i) of the gravity vector.
+    fn gravity_term(&self, i: usize) -> T {
+        if self.g == T::zero() {
+            return T::zero();
+        }
+        self.mass_sum_from(i) * self.g * self.lengths[i] * (self.angles[i] - self.gravity_angle).sin()
+    }
+
+    /// Writes -(C + D + G), the right-hand side of M α = -(C + D + G) before any external torques,
+    /// into an existing length-n vector. Matches the sum of the three `set_*` vectors entry for entry.
+    pub fn fill_generalized_forces(&self, rhs: &mut DVector<T>) {
+        let telescoping = self.is_telescoping();
         for i in 1..=self.n {
-            let m_val = self.mass_sum_from(i);
-            let term = m_val * self.g * self.lengths[i] * self.angles[i].sin();
-            g_vec[i - 1] = term;
+            let d_term = if telescoping { self.telescoping_term(i) } else { T::zero() };
+            rhs[i - 1] = -(self.centripetal_term(i) + d_term + self.gravity_term(i));
+        }
+    }
+
+    /// Kinetic energy T = ½ ωᵀ M ω (J)
+    /// Telescoping links add their radial motion:
+    /// Σ_ij μ_max(i,j) (l̇_i l_j ω_j sin(θ_i - θ_j) + ½ l̇_i l̇_j cos(θ_i - θ_j)).
+    pub fn kinetic_energy(&self) -> T {
+        let m_matrix = self.set_mass_matrix();
+        let omega = DVector::from_column_slice(&self.ang_vels[1..=self.n]);
+        let mut kinetic = T::from_f64(0.5) * omega.dot(&(m_matrix * &omega));
+
+        if self.is_telescoping() {
+            for i in 1..=self.n {
+                for j in 1..=self.n {
+                    let m_val = self.mass_sum_from(i.max(j));
+                    let diff = self.angles[i] - self.angles[j];
+                    kinetic += m_val
+                        * self.length_rates[i]
+                        * (self.lengths[j] * self.ang_vels[j] * diff.sin() + T::from_f64(0.5) * self.length_rates[j] * diff.cos());
+                }
+            }
         }
-        g_vec
+        kinetic
     }
-}
\ No newline at end of file
+
+    /// Potential energy V = Σ mⱼ g yⱼ with y measured downward-negative from the pivot (J)
+    /// y is taken against the gravity direction, so it follows `gravity_angle`.
+    pub fn potential_energy(&self) -> T {
+        let mut y = T::zero();
+        let mut v = T::zero();
+        for j in 1..=self.n {
+            y -= self.lengths[j] * (self.angles[j] - self.gravity_angle).cos();
+            v += self.masses[j] * self.g * y;
+        }
+        v
+    }
+
+    /// Total mechanical energy T + V (J)
+    pub fn total_energy(&self) -> T {
+        self.kinetic_energy() + self.potential_energy()
+    }
+}
+
+/// Failure of `solve_linear_system`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinAlgError {
+    /// Elimination found no usable pivot for this row (0-based): every remaining entry of its column
+    /// is negligible, so the matrix is singular to working precision.
+    Singular { pivot_row: usize },
+    /// A Cholesky factorization failed: the symmetric matrix is not positive definite to working
+    /// precision, e.g. a mass matrix whose tiny masses round away.
+    NotPositiveDefinite,
+}
+
+impl std::fmt::Display for LinAlgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinAlgError::Singular { pivot_row } => {
+                write!(f, "Linear system is singular (no usable pivot in row {})", pivot_row)
+            }
+            LinAlgError::NotPositiveDefinite => write!(f, "Matrix is not positive definite to working precision"),
+        }
+    }
+}
+
+impl std::error::Error for LinAlgError {}
+
+/// Pivots at most this fraction of the matrix's largest entry count as zero.
+/// Relative, so the test does not depend on the units of the masses and lengths.
+pub const PIVOT_EPSILON: f64 = 1e-14;
+
+/// Solves M x = rhs by Gaussian elimination with partial pivoting.
+/// Written out by hand (instead of nalgebra's LU) so it works for any `Real`, including `DoubleDouble`.
+/// A single pendulum (1x1 system) reduces to x = rhs / m.
+/// A NaN matrix (a blown-up state) is passed through as NaN for the divergence checks to catch.
+pub fn solve_linear_system<T: Real>(mut m: DMatrix<T>, mut rhs: DVector<T>) -> Result<DVector<T>, LinAlgError> {
+    solve_linear_system_in_place(&mut m, &mut rhs)?;
+    Ok(rhs)
+}
+
+/// `solve_linear_system` without allocating: `m` is left eliminated and `rhs` is overwritten with x.
+pub fn solve_linear_system_in_place<T: Real>(m: &mut DMatrix<T>, rhs: &mut DVector<T>) -> Result<(), LinAlgError> {
+    let size = rhs.len();
+    let threshold = PIVOT_EPSILON * m.iter().fold(0.0_f64, |acc, x| acc.max(x.abs().to_f64()));
+
+    for col in 0..size {
+        // Pick the largest remaining entry in this column as pivot
+        let pivot_row = (col..size)
+            .max_by(|&a, &b| m[(a, col)].abs().partial_cmp(&m[(b, col)].abs()).unwrap_or(std::cmp::Ordering::Equal))
+            .unwrap_or(col);
+        if m[(pivot_row, col)].abs().to_f64() <= threshold {
+            return Err(LinAlgError::Singular { pivot_row: col });
+        }
+        if pivot_row != col {
+            m.swap_rows(pivot_row, col);
+            rhs.swap_rows(pivot_row, col);
+        }
+
+        let pivot = m[(col, col)];
+        for row in col + 1..size {
+            let factor = m[(row, col)] / pivot;
+            for k in col..size {
+                let v = m[(col, k)];
+                m[(row, k)] -= factor * v;
+            }
+            let v = rhs[col];
+            rhs[row] -= factor * v;
+        }
+    }
+
+    // Back substitution, x replacing rhs from the bottom up
+    for row in (0..size).rev() {
+        let mut acc = rhs[row];
+        for k in row + 1..size {
+            acc -= m[(row, k)] * rhs[k];
+        }
+        rhs[row] = acc / m[(row, row)];
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symmetry_check_catches_a_mutated_entry() {
+        let angles = vec![0.0, 0.7, -1.2, 2.5];
+        let math = NPendulumMath::new(3, vec![0.0, 1.0, 0.4, 2.0], vec![0.0, 1.0, 0.6, 1.3], angles, vec![0.0; 4], STANDARD_GRAVITY);
+        let mut m = math.set_mass_matrix();
+        assert!(NPendulumMath::is_symmetric(&m, 1e-12));
+
+        m[(0, 2)] += 1e-6;
+        assert!(!NPendulumMath::is_symmetric(&m, 1e-9));
+        assert!(!NPendulumMath::<f64>::is_symmetric(&DMatrix::zeros(2, 3), 1e-9));
+    }
+
+    #[test]
+    fn rank_deficient_system_is_reported() {
+        // [[1, 2], [2, 4]] has no second pivot
+        let result = solve_linear_system(DMatrix::from_row_slice(2, 2, &[1.0, 2.0, 2.0, 4.0]), DVector::from_vec(vec![1.0, 1.0]));
+        assert_eq!(result.err(), Some(LinAlgError::Singular { pivot_row: 1 }));
+    }
+}
@@ -0,0 +1,732 @@
+// src/plot.rs
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{codecs::png::PngEncoder, Delay, ExtendedColorType, Frame, ImageEncoder, RgbaImage};
+use plotters::coord::Shift;
+use plotters::element::DashedPathElement;
+use plotters::prelude::*;
+use serde::Deserialize;
+use std::path::Path;
+
+pub const PLOT_SIZE: (u32, u32) = (500, 500);
+
+/// Default trajectory colors, matching the frontend palette.
+const DEFAULT_COLORS: [RGBColor; 7] = [
+    RGBColor(0x1f, 0x77, 0xb4),
+    RGBColor(0xd6, 0x27, 0x28),
+    RGBColor(0x2c, 0xa0, 0x2c),
+    RGBColor(0x17, 0xbe, 0xcf),
+    RGBColor(0x94, 0x67, 0xbd),
+    RGBColor(0xe3, 0x77, 0xc2),
+    RGBColor(0xbc, 0xbd, 0x22),
+];
+
+/// Okabe-Ito colors, distinguishable under the common forms of color blindness.
+/// Their yellow is last since it is the hardest to see on white.
+const COLORBLIND_COLORS: [RGBColor; 8] = [
+    RGBColor(0x00, 0x72, 0xb2),
+    RGBColor(0xe6, 0x9f, 0x00),
+    RGBColor(0x00, 0x9e, 0x73),
+    RGBColor(0xd5, 0x5e, 0x00),
+    RGBColor(0xcc, 0x79, 0xa7),
+    RGBColor(0x56, 0xb4, 0xe9),
+    RGBColor(0x00, 0x00, 0x00),
+    RGBColor(0xf0, 0xe4, 0x42),
+];
+
+/// Gray levels for print, dark enough to stay visible at the trajectories' 75% opacity.
+const GRAY_LEVELS: [u8; 3] = [0x00, 0x60, 0xa0];
+
+/// Dash patterns (dash, gap in px at `PLOT_SIZE`) cycled through once the gray levels run out.
+const DASH_PATTERNS: [Option<(u32, u32)>; 3] = [None, Some((6, 3)), Some((2, 3))];
+
+/// Named color scheme for the per-link defaults.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorPalette {
+    #[default]
+    Default,
+    Colorblind,
+    /// Gray levels combined with dash patterns, so links stay apart in black-and-white print
+    Grayscale,
+}
+
+impl ColorPalette {
+    /// Color and dash pattern of link k (0-based).
+    /// The color lists fall back to `Palette99` past their end; grayscale repeats after 9 links.
+    fn style(self, k: usize) -> (RGBColor, Option<(u32, u32)>) {
+        let palette99 = || {
+            let c = Palette99::pick(k).to_rgba();
+            RGBColor(c.0, c.1, c.2)
+        };
+        match self {
+            ColorPalette::Default => (DEFAULT_COLORS.get(k).copied().unwrap_or_else(palette99), None),
+            ColorPalette::Colorblind => (COLORBLIND_COLORS.get(k).copied().unwrap_or_else(palette99), None),
+            ColorPalette::Grayscale => {
+                let level = GRAY_LEVELS[k % GRAY_LEVELS.len()];
+                let dash = DASH_PATTERNS[(k / GRAY_LEVELS.len()) % DASH_PATTERNS.len()];
+                (RGBColor(level, level, level), dash)
+            }
+        }
+    }
+}
+
+/// Vertical orientation of the trajectory plot; only the drawing is flipped, never the data.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum YAxis {
+    /// Physics coordinates as computed: the hanging chain appears below the pivot
+    #[default]
+    Down,
+    /// y negated before drawing: the hanging chain appears above the pivot
+    Up,
+}
+
+impl YAxis {
+    fn apply(self, (x, y): (f64, f64)) -> (f64, f64) {
+        match self {
+            YAxis::Down => (x, y),
+            YAxis::Up => (x, -y),
+        }
+    }
+}
+
+/// Per-link appearance of the trajectory plot.
+pub struct SeriesStyle {
+    pub color: RGBColor,
+    pub label: String,
+    pub dash: Option<(u32, u32)>, // (dash, gap) in px at `PLOT_SIZE`, solid when None
+}
+
+/// Straight arrow from `from` to `to` in plot coordinates (m).
+#[derive(Clone, Copy, Debug)]
+pub struct Arrow {
+    pub from: (f64, f64),
+    pub to: (f64, f64),
+}
+
+/// Everything drawn on the trajectory plot.
+pub struct TrajectoryPlot<'a> {
+    pub positions: &'a [Vec<f64>], // [x1, y1, x2, y2, ...] per time step
+    pub n: usize,
+    pub limit: f64,                // Both axes span -limit..limit (m)
+    pub styles: &'a [SeriesStyle],
+    pub shown: &'a [bool],         // Per bob, whether its path (and arrow) is drawn
+    pub line_width: Option<f64>,   // Path width (px at PLOT_SIZE); None draws 1 px, 2 px for the outermost bob
+    pub line_opacity: f64,         // Path opacity in [0, 1]
+    pub simplify_tolerance: Option<f64>, // Ramer-Douglas-Peucker tolerance (m) for the drawn paths, None draws every sample
+    pub arrows: &'a [Arrow],       // One per bob, may be empty
+    pub center_of_mass: &'a [(f64, f64)], // System center-of-mass path, may be empty
+    pub caption: &'a str,          // Empty draws no caption
+    pub y_axis: YAxis,
+}
+
+/// Ramer-Douglas-Peucker: indices of the points kept when `points` is simplified so that every
+/// dropped point lies within `tolerance` of the segment between the kept points around it.
+/// The first and last points are always kept. Uses distance to the segment rather than to the
+/// infinite line, so the bound also holds where a path doubles back. Iterative, so long paths
+/// cannot overflow the stack.
+fn simplify_path_indices(points: &[(f64, f64)], tolerance: f64) -> Vec<usize> {
+    if points.len() < 3 {
+        return (0..points.len()).collect();
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let farthest = (first + 1..last)
+            .map(|i| (i, segment_distance(points[i], points[first], points[last])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, distance)) = farthest {
+            if distance > tolerance {
+                keep[i] = true;
+                stack.push((first, i));
+                stack.push((i, last));
+            }
+        }
+    }
+    (0..points.len()).filter(|&i| keep[i]).collect()
+}
+
+/// The points `simplify_path_indices` keeps.
+pub fn simplify_path(points: &[(f64, f64)], tolerance: f64) -> Vec<(f64, f64)> {
+    simplify_path_indices(points, tolerance).into_iter().map(|i| points[i]).collect()
+}
+
+/// Distance from `p` to the segment a-b.
+fn segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+    let s = if length_sq > 0.0 {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (p.0 - a.0 - s * dx).hypot(p.1 - a.1 - s * dy)
+}
+
+/// Builds one velocity arrow per bob: it starts at the bob and spans `scale` seconds of motion,
+/// so a bob at v m/s gets an arrow `scale · v` meters long.
+pub fn velocity_arrows(positions: &[(f64, f64)], velocities: &[(f64, f64)], scale: f64) -> Vec<Arrow> {
+    positions
+        .iter()
+        .zip(velocities)
+        .map(|(&(x, y), &(vx, vy))| Arrow {
+            from: (x, y),
+            to: (x + scale * vx, y + scale * vy),
+        })
+        .collect()
+}
+
+/// Helper: Parses "#rrggbb" (leading '#' optional) into a color.
+pub fn parse_hex_color(s: &str) -> Option<RGBColor> {
+    let hex = s.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(RGBColor(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Builds one style per link: custom colors/labels where given, `palette` defaults otherwise.
+/// Custom colors only replace the color; the palette's dash pattern is kept.
+pub fn series_styles(n: usize, palette: ColorPalette, colors: &[RGBColor], labels: &[String]) -> Vec<SeriesStyle> {
+    (0..n)
+        .map(|k| {
+            let (default_color, dash) = palette.style(k);
+            let color = colors.get(k).copied().unwrap_or(default_color);
+            let label = labels
+                .get(k)
+                .cloned()
+                .unwrap_or_else(|| format!("Pendulum {}", k + 1));
+            SeriesStyle { color, label, dash }
+        })
+        .collect()
+}
+
+/// Draws every bob's path onto any plotters drawing area.
+/// `positions` holds one [x1, y1, x2, y2, ...] row per time step.
+/// `plot.arrows` are overlaid in the matching link color.
+/// Every pixel size (fonts, margins, strokes) is multiplied by `scale`, so a drawing area
+/// `scale` times larger than `PLOT_SIZE` keeps the same layout (see `supersample`).
+pub fn draw_trajectories<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    plot: &TrajectoryPlot,
+    scale: u32,
+) -> Result<(), String> {
+    let TrajectoryPlot {
+        positions,
+        n,
+        limit,
+        styles,
+        shown,
+        line_width,
+        line_opacity,
+        simplify_tolerance,
+        arrows,
+        center_of_mass,
+        caption,
+        y_axis,
+    } = *plot;
+    let simplify = |path: Vec<(f64, f64)>| match simplify_tolerance {
+        Some(tolerance) => simplify_path(&path, tolerance),
+        None => path,
+    };
+    let px = |v: u32| v * scale;
+    root.fill(&WHITE).map_err(|e| e.to_string())?;
+
+    let mut builder = ChartBuilder::on(root);
+    if !caption.is_empty() {
+        builder.caption(caption, ("sans-serif", px(20)));
+    }
+    let mut chart = builder
+        .margin(px(10))
+        .x_label_area_size(px(30))
+        .y_label_area_size(px(40))
+        .build_cartesian_2d(-limit..limit, -limit..limit)
+        .map_err(|e| e.to_string())?;
+
+    // plotters' defaults at PLOT_SIZE: 12 px labels, 5 px ticks, 1 px axes
+    chart
+        .configure_mesh()
+        .label_style(("sans-serif", px(12)))
+        .set_all_tick_mark_size(px(5))
+        .axis_style(BLACK.stroke_width(px(1)))
+        .draw()
+        .map_err(|e| e.to_string())?;
+
+    let legend_len = px(15) as i32;
+    for (k, style) in styles.iter().enumerate().take(n).filter(|&(k, _)| shown[k]) {
+        // The outermost bob is drawn thicker by default since its path is usually the one of interest
+        let width = match line_width {
+            Some(w) => ((w * scale as f64).round() as u32).max(1),
+            None => px(if k + 1 == n { 2 } else { 1 }),
+        };
+        let color = style.color;
+        let path = simplify(positions.iter().map(|p| y_axis.apply((p[2 * k], p[2 * k + 1]))).collect());
+        let line_style = color.mix(line_opacity).stroke_width(width);
+        match style.dash {
+            None => chart
+                .draw_series(LineSeries::new(path, line_style))
+                .map_err(|e| e.to_string())?
+                .label(style.label.clone())
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + legend_len, y)], color.stroke_width(px(2)))),
+            Some((dash, gap)) => chart
+                .draw_series(DashedLineSeries::new(path, px(dash), px(gap), line_style))
+                .map_err(|e| e.to_string())?
+                .label(style.label.clone())
+                .legend(move |(x, y)| {
+                    DashedPathElement::new(vec![(x, y), (x + legend_len, y)], px(dash), px(gap), color.stroke_width(px(2)))
+                }),
+        };
+    }
+
+    if !center_of_mass.is_empty() {
+        let path = simplify(center_of_mass.iter().map(|&p| y_axis.apply(p)).collect());
+        chart
+            .draw_series(LineSeries::new(path, BLACK.stroke_width(px(1))))
+            .map_err(|e| e.to_string())?
+            .label("Center of mass")
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + legend_len, y)], BLACK.stroke_width(px(2))));
+    }
+
+    for ((arrow, style), _) in arrows.iter().zip(styles).zip(shown).take(n).filter(|&(_, &show)| show) {
+        chart
+            .draw_series(
+                arrow_segments(arrow).into_iter().map(|seg| {
+                    let seg: Vec<(f64, f64)> = seg.into_iter().map(|p| y_axis.apply(p)).collect();
+                    PathElement::new(seg, style.color.stroke_width(px(2)))
+                }),
+            )
+            .map_err(|e| e.to_string())?;
+    }
+
+    chart
+        .configure_series_labels()
+        .label_font(("sans-serif", px(12)))
+        .margin(px(10))
+        .legend_area_size(px(30))
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK.stroke_width(px(1)))
+        .draw()
+        .map_err(|e| e.to_string())?;
+
+    root.present().map_err(|e| e.to_string())
+}
+
+/// Renders the trajectory plot into memory and returns it as a base64 PNG (web responses).
+/// `supersample` is the antialiasing factor, see `supersample`.
+pub fn trajectory_png_base64(plot: &TrajectoryPlot, supersample: u32) -> Result<String, String> {
+    let rgb = self::supersample(supersample, |root| draw_trajectories(root, plot, supersample))?;
+    let (w, h) = PLOT_SIZE;
+    Ok(STANDARD.encode(encode_png(&rgb, w, h)?))
+}
+
+/// Renders the trajectory plot straight to an image file; the format follows the extension.
+pub fn trajectory_png_file(path: &Path, plot: &TrajectoryPlot, supersample: u32) -> Result<(), String> {
+    let rgb = self::supersample(supersample, |root| draw_trajectories(root, plot, supersample))?;
+    let (w, h) = PLOT_SIZE;
+    image::save_buffer(path, &rgb, w, h, ExtendedColorType::Rgb8).map_err(|e| e.to_string())
+}
+
+/// Draws at `factor` times `PLOT_SIZE` and box-filters every factor x factor block down to one
+/// pixel, returning a `PLOT_SIZE` RGB buffer. plotters rasterizes without antialiasing, so this
+/// smooths jagged 1 px trajectories; the drawing routine must scale its pixel sizes by `factor`.
+/// Memory and drawing time grow with factor².
+pub fn supersample<F>(factor: u32, draw: F) -> Result<Vec<u8>, String>
+where
+    F: FnOnce(&DrawingArea<BitMapBackend, Shift>) -> Result<(), String>,
+{
+    let factor = factor.max(1);
+    let (w, h) = PLOT_SIZE;
+    let (big_w, big_h) = (w * factor, h * factor);
+    let mut big = vec![0u8; (big_w * big_h * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut big, (big_w, big_h)).into_drawing_area();
+        draw(&root)?;
+    }
+    if factor == 1 {
+        return Ok(big);
+    }
+
+    let (w, h, f) = (w as usize, h as usize, factor as usize);
+    let mut rgb = vec![0u8; w * h * 3];
+    for y in 0..h {
+        for x in 0..w {
+            for c in 0..3 {
+                let mut sum = 0u32;
+                for dy in 0..f {
+                    let row = (y * f + dy) * w * f;
+                    for dx in 0..f {
+                        sum += u32::from(big[(row + x * f + dx) * 3 + c]);
+                    }
+                }
+                rgb[(y * w + x) * 3 + c] = ((sum + (f * f / 2) as u32) / (f * f) as u32) as u8;
+            }
+        }
+    }
+    Ok(rgb)
+}
+
+/// Renders named (x, y) series as connected lines with point markers, auto-scaling both axes.
+pub fn xy_plot_png_base64(
+    caption: &str,
+    x_desc: &str,
+    y_desc: &str,
+    series: &[(String, Vec<(f64, f64)>)],
+) -> Result<String, String> {
+    png_base64(|root| draw_chart(root, caption, x_desc, y_desc, series, true))
+}
+
+/// Renders named (x, y) point clouds without connecting lines, e.g. bifurcation diagrams.
+pub fn scatter_plot_png_base64(
+    caption: &str,
+    x_desc: &str,
+    y_desc: &str,
+    series: &[(String, Vec<(f64, f64)>)],
+) -> Result<String, String> {
+    png_base64(|root| draw_chart(root, caption, x_desc, y_desc, series, false))
+}
+
+/// Draws an auto-scaled line or scatter chart onto any plotters drawing area.
+pub fn draw_chart<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    caption: &str,
+    x_desc: &str,
+    y_desc: &str,
+    series: &[(String, Vec<(f64, f64)>)],
+    connect: bool,
+) -> Result<(), String> {
+    let (x_range, y_range) = padded_ranges(series.iter().flat_map(|(_, pts)| pts.iter().copied()));
+
+    root.fill(&WHITE).map_err(|e| e.to_string())?;
+
+    let mut chart = ChartBuilder::on(root)
+        .caption(caption, ("sans-serif", 20))
+        .margin(10)
+        .x_label_area_size(35)
+        .y_label_area_size(55)
+        .build_cartesian_2d(x_range, y_range)
+        .map_err(|e| e.to_string())?;
+
+    chart
+        .configure_mesh()
+        .x_desc(x_desc)
+        .y_desc(y_desc)
+        .draw()
+        .map_err(|e| e.to_string())?;
+
+    let styles = series_styles(series.len(), ColorPalette::Default, &[], &[]);
+    for ((name, points), style) in series.iter().zip(&styles) {
+        let color = style.color;
+        if connect {
+            chart
+                .draw_series(LineSeries::new(points.iter().copied(), color.stroke_width(1)))
+                .map_err(|e| e.to_string())?;
+        }
+        chart
+            .draw_series(points.iter().map(|&p| Circle::new(p, if connect { 2 } else { 1 }, color.filled())))
+            .map_err(|e| e.to_string())?
+            .label(name.clone())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 15, y)], color.stroke_width(2)));
+    }
+
+    if series.len() > 1 {
+        chart
+            .configure_series_labels()
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()
+            .map_err(|e| e.to_string())?;
+    }
+
+    root.present().map_err(|e| e.to_string())
+}
+
+/// Renders total energy over time with the drift band, see `draw_energy`.
+pub fn energy_png_base64(t: &[f64], energy: &[f64]) -> Result<String, String> {
+    png_base64(|root| draw_energy(root, t, energy))
+}
+
+/// Draws E(t) on the left axis and shades the relative drift (E - E0) / |E0| against a
+/// secondary right axis, so a leaking integrator shows up as a growing band.
+/// With E0 = 0 the band shows the absolute drift E - E0 instead.
+pub fn draw_energy<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    t: &[f64],
+    energy: &[f64],
+) -> Result<(), String> {
+    let e0 = energy.first().copied().unwrap_or(0.0);
+    let scale = if e0 != 0.0 { e0.abs() } else { 1.0 };
+    let energy_points: Vec<(f64, f64)> = t.iter().copied().zip(energy.iter().copied()).collect();
+    let drift_points: Vec<(f64, f64)> = energy_points.iter().map(|&(x, e)| (x, (e - e0) / scale)).collect();
+
+    let (x_range, e_range) = padded_ranges(energy_points.iter().copied());
+    // Keep zero drift inside the band's axis so the shading always starts from the baseline
+    let (_, drift_range) = padded_ranges(drift_points.iter().copied().chain([(0.0, 0.0)]));
+
+    root.fill(&WHITE).map_err(|e| e.to_string())?;
+
+    let mut chart = ChartBuilder::on(root)
+        .caption("Energy", ("sans-serif", 20))
+        .margin(10)
+        .x_label_area_size(35)
+        .y_label_area_size(55)
+        .right_y_label_area_size(65)
+        .build_cartesian_2d(x_range.clone(), e_range)
+        .map_err(|e| e.to_string())?
+        .set_secondary_coord(x_range, drift_range);
+
+    chart
+        .configure_mesh()
+        .x_desc("t (s)")
+        .y_desc("E (J)")
+        .draw()
+        .map_err(|e| e.to_string())?;
+    chart
+        .configure_secondary_axes()
+        .y_desc(if e0 != 0.0 { "(E - E0) / |E0|" } else { "E - E0 (J)" })
+        .draw()
+        .map_err(|e| e.to_string())?;
+
+    let band = DEFAULT_COLORS[1];
+    chart
+        .draw_secondary_series(AreaSeries::new(drift_points, 0.0, band.mix(0.25)).border_style(band.stroke_width(1)))
+        .map_err(|e| e.to_string())?
+        .label("Relative drift")
+        .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 15, y + 5)], band.mix(0.25).filled()));
+
+    let line = DEFAULT_COLORS[0];
+    chart
+        .draw_series(LineSeries::new(energy_points, line.stroke_width(2)))
+        .map_err(|e| e.to_string())?
+        .label("Total energy")
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 15, y)], line.stroke_width(2)));
+
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .map_err(|e| e.to_string())?;
+
+    root.present().map_err(|e| e.to_string())
+}
+
+/// Draws one animation frame of several chains sharing the same axes: rods from the pivot
+/// through every bob, with the bobs as dots. `chains` holds the bob positions of each chain.
+pub fn draw_chains<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    chains: &[Vec<(f64, f64)>],
+    limit: f64,
+    caption: &str,
+) -> Result<(), String> {
+    root.fill(&WHITE).map_err(|e| e.to_string())?;
+
+    let mut chart = ChartBuilder::on(root)
+        .caption(caption, ("sans-serif", 16))
+        .margin(10)
+        .build_cartesian_2d(-limit..limit, -limit..limit)
+        .map_err(|e| e.to_string())?;
+
+    let styles = series_styles(chains.len(), ColorPalette::Default, &[], &[]);
+    for (bobs, style) in chains.iter().zip(&styles) {
+        let color = style.color;
+        let path: Vec<(f64, f64)> = std::iter::once((0.0, 0.0)).chain(bobs.iter().copied()).collect();
+        chart
+            .draw_series(std::iter::once(PathElement::new(path, color.mix(0.8).stroke_width(2))))
+            .map_err(|e| e.to_string())?;
+        chart
+            .draw_series(bobs.iter().map(|&p| Circle::new(p, 3, color.filled())))
+            .map_err(|e| e.to_string())?;
+    }
+
+    root.present().map_err(|e| e.to_string())
+}
+
+/// Renders `frames` (one `draw_chains` input each) into a looping GIF and returns it as base64.
+/// `size` is the square frame edge in pixels, `delay_ms` the time each frame is shown.
+pub fn chains_gif_base64(
+    frames: &[Vec<Vec<(f64, f64)>>],
+    limit: f64,
+    size: u32,
+    delay_ms: u32,
+    caption: &str,
+) -> Result<String, String> {
+    gif_base64((size, size), frames.len(), delay_ms, |i, root| draw_chains(root, &frames[i], limit, caption))
+}
+
+/// Renders a single chain swinging (left panel) next to its energy up to that moment (right panel),
+/// one GIF frame per entry of `frames`: (sample index into `t`/`energy`, bob positions there).
+/// Both panels keep the axes of the whole run so nothing jumps between frames.
+/// Each panel is `size` pixels square.
+pub fn motion_energy_gif_base64(
+    frames: &[(usize, Vec<(f64, f64)>)],
+    t: &[f64],
+    energy: &[f64],
+    limit: f64,
+    size: u32,
+    delay_ms: u32,
+) -> Result<String, String> {
+    let (x_range, e_range) = padded_ranges(t.iter().copied().zip(energy.iter().copied()));
+    gif_base64((2 * size, size), frames.len(), delay_ms, |i, root| {
+        let (index, bobs) = &frames[i];
+        let (left, right) = root.split_horizontally(size);
+        draw_chains(&left, std::slice::from_ref(bobs), limit, &format!("t = {:.2} s", t[*index]))?;
+        draw_energy_so_far(&right, &t[..=*index], &energy[..=*index], x_range.clone(), e_range.clone())
+    })
+}
+
+/// Helper: E(t) over the given samples on fixed axes, with a dot on the latest one.
+fn draw_energy_so_far<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    t: &[f64],
+    energy: &[f64],
+    x_range: std::ops::Range<f64>,
+    e_range: std::ops::Range<f64>,
+) -> Result<(), String> {
+    root.fill(&WHITE).map_err(|e| e.to_string())?;
+
+    let mut chart = ChartBuilder::on(root)
+        .caption("Energy", ("sans-serif", 16))
+        .margin(10)
+        .x_label_area_size(35)
+        .y_label_area_size(55)
+        .build_cartesian_2d(x_range, e_range)
+        .map_err(|e| e.to_string())?;
+
+    chart
+        .configure_mesh()
+        .x_desc("t (s)")
+        .y_desc("E (J)")
+        .draw()
+        .map_err(|e| e.to_string())?;
+
+    let line = DEFAULT_COLORS[0];
+    chart
+        .draw_series(LineSeries::new(t.iter().copied().zip(energy.iter().copied()), line.stroke_width(2)))
+        .map_err(|e| e.to_string())?;
+    chart
+        .draw_series(t.last().zip(energy.last()).map(|(&x, &e)| Circle::new((x, e), 4, line.filled())))
+        .map_err(|e| e.to_string())?;
+
+    root.present().map_err(|e| e.to_string())
+}
+
+/// Helper: Draws `frames` frames of `width` x `height` pixels with `draw(i, root)` and encodes
+/// them as a looping GIF, returned as base64. Each frame is shown for `delay_ms`.
+fn gif_base64<F>((width, height): (u32, u32), frames: usize, delay_ms: u32, mut draw: F) -> Result<String, String>
+where
+    F: FnMut(usize, &DrawingArea<BitMapBackend, Shift>) -> Result<(), String>,
+{
+    let mut gif = Vec::new();
+    {
+        // Speed 10 trades a little palette quality for much faster quantization
+        let mut encoder = GifEncoder::new_with_speed(&mut gif, 10);
+        encoder.set_repeat(Repeat::Infinite).map_err(|e| e.to_string())?;
+        for i in 0..frames {
+            let mut rgb = vec![0u8; (width * height * 3) as usize];
+            {
+                let root = BitMapBackend::with_buffer(&mut rgb, (width, height)).into_drawing_area();
+                draw(i, &root)?;
+            }
+            let rgba: Vec<u8> = rgb.chunks_exact(3).flat_map(|px| [px[0], px[1], px[2], 255]).collect();
+            let image = RgbaImage::from_raw(width, height, rgba).ok_or("Frame buffer has the wrong size")?;
+            encoder
+                .encode_frame(Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(delay_ms, 1)))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(STANDARD.encode(gif))
+}
+
+/// Helper: Shaft plus the two barbs of the arrowhead (25% of the shaft, ±25°).
+fn arrow_segments(arrow: &Arrow) -> Vec<Vec<(f64, f64)>> {
+    let (x0, y0) = arrow.from;
+    let (x1, y1) = arrow.to;
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    let mut segments = vec![vec![(x0, y0), (x1, y1)]];
+    if dx == 0.0 && dy == 0.0 {
+        return segments; // Bob at rest: no direction to point in
+    }
+
+    let head = 0.25;
+    let (sin, cos) = 25f64.to_radians().sin_cos();
+    for side in [1.0, -1.0] {
+        // Back along the shaft, rotated by ±25°
+        let bx = -(dx * cos - side * dy * sin) * head;
+        let by = -(side * dx * sin + dy * cos) * head;
+        segments.push(vec![(x1, y1), (x1 + bx, y1 + by)]);
+    }
+    segments
+}
+
+/// Helper: Runs a drawing routine against an in-memory bitmap and returns the PNG as base64.
+fn png_base64<F>(draw: F) -> Result<String, String>
+where
+    F: FnOnce(&DrawingArea<BitMapBackend, Shift>) -> Result<(), String>,
+{
+    let (w, h) = PLOT_SIZE;
+    let mut buffer = vec![0u8; (w * h * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (w, h)).into_drawing_area();
+        draw(&root)?;
+    }
+    Ok(STANDARD.encode(encode_png(&buffer, w, h)?))
+}
+
+/// Helper: Axis ranges covering all points with 5% padding (non-finite points are ignored).
+fn padded_ranges(points: impl Iterator<Item = (f64, f64)>) -> (std::ops::Range<f64>, std::ops::Range<f64>) {
+    let (mut x_min, mut x_max, mut y_min, mut y_max) = (f64::MAX, f64::MIN, f64::MAX, f64::MIN);
+    for (x, y) in points.filter(|(x, y)| x.is_finite() && y.is_finite()) {
+        x_min = x_min.min(x);
+        x_max = x_max.max(x);
+        y_min = y_min.min(y);
+        y_max = y_max.max(y);
+    }
+    let pad = |lo: f64, hi: f64| {
+        if lo > hi {
+            return -1.0..1.0; // No finite points
+        }
+        let margin = if hi > lo { 0.05 * (hi - lo) } else { 0.5 * lo.abs().max(1.0) };
+        (lo - margin)..(hi + margin)
+    };
+    (pad(x_min, x_max), pad(y_min, y_max))
+}
+
+/// Helper: Encodes a raw RGB buffer as PNG.
+fn encode_png(rgb: &[u8], w: u32, h: u32) -> Result<Vec<u8>, String> {
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png)
+        .write_image(rgb, w, h, ExtendedColorType::Rgb8)
+        .map_err(|e| e.to_string())?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::NPendulumSolver;
+    use crate::math::STANDARD_GRAVITY;
+
+    #[test]
+    fn simplified_path_stays_within_tolerance() {
+        // Outer bob of a chaotic double pendulum (1 kg, 1 m, released at 120° and 0°, 20 s at dt = 5e-3)
+        // simplified to 1 mm: every dropped sample is measured against the segment between the kept
+        // samples around it.
+        let tolerance = 1e-3;
+        let solver = NPendulumSolver::new(2, vec![0.0, 1.0, 1.0], vec![0.0, 1.0, 1.0], STANDARD_GRAVITY);
+        let (t, sol) = solver.solve(vec![0.0, 120f64.to_radians(), 0.0], vec![0.0; 3], 20.0, 4001).expect("test chain is nonsingular");
+        let path: Vec<(f64, f64)> = solver.cartesian_positions(&t, &sol).iter().map(|row| (row[2], row[3])).collect();
+
+        let kept = simplify_path_indices(&path, tolerance);
+        assert!(kept.len() < path.len(), "kept all {} points", path.len());
+        for w in kept.windows(2) {
+            for i in w[0] + 1..w[1] {
+                let deviation = segment_distance(path[i], path[w[0]], path[w[1]]);
+                assert!(deviation <= tolerance, "point {} is {:e} m off", i, deviation);
+            }
+        }
+    }
+}
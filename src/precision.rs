@@ -0,0 +1,249 @@
+// src/precision.rs
+use num_traits::{One, Zero};
+use std::fmt::Debug;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// Scalar type the physics and the integrator can run in.
+/// `f64` is the production type; `DoubleDouble` is for reference runs.
+pub trait Real:
+    Copy
+    + Debug
+    + PartialEq
+    + PartialOrd
+    + Zero
+    + One
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+    + 'static
+{
+    fn from_f64(v: f64) -> Self;
+    fn to_f64(self) -> f64;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn abs(self) -> Self;
+}
+
+impl Real for f64 {
+    fn from_f64(v: f64) -> Self {
+        v
+    }
+    fn to_f64(self) -> f64 {
+        self
+    }
+    fn sin(self) -> Self {
+        f64::sin(self)
+    }
+    fn cos(self) -> Self {
+        f64::cos(self)
+    }
+    fn abs(self) -> Self {
+        f64::abs(self)
+    }
+}
+
+/// Unevaluated sum hi + lo of two f64 with |lo| <= ulp(hi) / 2, giving ~32 significant digits.
+/// Arithmetic follows the classic error-free transformations (Dekker / Knuth two-sum, FMA two-prod).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DoubleDouble {
+    pub hi: f64,
+    pub lo: f64,
+}
+
+const DD_PI_2: DoubleDouble = DoubleDouble {
+    hi: std::f64::consts::FRAC_PI_2,
+    lo: 6.123233995736766e-17,
+};
+
+/// Helper: s + e == a + b exactly.
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    let bb = s - a;
+    (s, (a - (s - bb)) + (b - bb))
+}
+
+/// Helper: two_sum when |a| >= |b|.
+fn quick_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    (s, b - (s - a))
+}
+
+/// Helper: p + e == a * b exactly.
+fn two_prod(a: f64, b: f64) -> (f64, f64) {
+    let p = a * b;
+    (p, a.mul_add(b, -p))
+}
+
+impl DoubleDouble {
+    fn renormalize(hi: f64, lo: f64) -> Self {
+        let (hi, lo) = quick_two_sum(hi, lo);
+        Self { hi, lo }
+    }
+
+    /// Taylor series for |r| <= π/4; returns (sin r, cos r).
+    fn sin_cos_reduced(r: Self) -> (Self, Self) {
+        let r2 = r * r;
+        let eps = 1e-34;
+
+        let mut sin = r;
+        let mut term = r;
+        for i in 1..30 {
+            let k = (2 * i) as f64;
+            term = -(term * r2) / Self::from_f64(k * (k + 1.0));
+            sin += term;
+            if term.hi.abs() < eps {
+                break;
+            }
+        }
+
+        let mut cos = Self::one();
+        let mut term = Self::one();
+        for i in 1..30 {
+            let k = (2 * i) as f64;
+            term = -(term * r2) / Self::from_f64((k - 1.0) * k);
+            cos += term;
+            if term.hi.abs() < eps {
+                break;
+            }
+        }
+        (sin, cos)
+    }
+
+    /// Reduces x by multiples of π/2 and evaluates both functions in the right quadrant.
+    fn sin_cos(self) -> (Self, Self) {
+        let k = (self.hi / std::f64::consts::FRAC_PI_2).round();
+        let r = self - DD_PI_2 * Self::from_f64(k);
+        let (s, c) = Self::sin_cos_reduced(r);
+        match (k as i64).rem_euclid(4) {
+            0 => (s, c),
+            1 => (c, -s),
+            2 => (-s, -c),
+            _ => (-c, s),
+        }
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = Self;
+    fn add(self, b: Self) -> Self {
+        let (s, e) = two_sum(self.hi, b.hi);
+        let (t, f) = two_sum(self.lo, b.lo);
+        let (s, e) = quick_two_sum(s, e + t);
+        Self::renormalize(s, e + f)
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = Self;
+    fn sub(self, b: Self) -> Self {
+        self + (-b)
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = Self;
+    fn mul(self, b: Self) -> Self {
+        let (p, e) = two_prod(self.hi, b.hi);
+        Self::renormalize(p, e + (self.hi * b.lo + self.lo * b.hi))
+    }
+}
+
+impl Div for DoubleDouble {
+    type Output = Self;
+    fn div(self, b: Self) -> Self {
+        // Long division: three f64 quotient digits
+        let q1 = self.hi / b.hi;
+        let r = self - b * Self::from_f64(q1);
+        let q2 = r.hi / b.hi;
+        let r = r - b * Self::from_f64(q2);
+        let q3 = r.hi / b.hi;
+        let (q1, q2) = quick_two_sum(q1, q2);
+        Self { hi: q1, lo: q2 } + Self::from_f64(q3)
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl AddAssign for DoubleDouble {
+    fn add_assign(&mut self, b: Self) {
+        *self = *self + b;
+    }
+}
+
+impl SubAssign for DoubleDouble {
+    fn sub_assign(&mut self, b: Self) {
+        *self = *self - b;
+    }
+}
+
+impl MulAssign for DoubleDouble {
+    fn mul_assign(&mut self, b: Self) {
+        *self = *self * b;
+    }
+}
+
+impl DivAssign for DoubleDouble {
+    fn div_assign(&mut self, b: Self) {
+        *self = *self / b;
+    }
+}
+
+impl PartialOrd for DoubleDouble {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        match self.hi.partial_cmp(&other.hi) {
+            Some(std::cmp::Ordering::Equal) => self.lo.partial_cmp(&other.lo),
+            ord => ord,
+        }
+    }
+}
+
+impl Zero for DoubleDouble {
+    fn zero() -> Self {
+        Self { hi: 0.0, lo: 0.0 }
+    }
+    fn is_zero(&self) -> bool {
+        self.hi == 0.0
+    }
+}
+
+impl One for DoubleDouble {
+    fn one() -> Self {
+        Self { hi: 1.0, lo: 0.0 }
+    }
+}
+
+impl Real for DoubleDouble {
+    fn from_f64(v: f64) -> Self {
+        Self { hi: v, lo: 0.0 }
+    }
+    fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+    fn sin(self) -> Self {
+        self.sin_cos().0
+    }
+    fn cos(self) -> Self {
+        self.sin_cos().1
+    }
+    fn abs(self) -> Self {
+        if self.hi < 0.0 {
+            -self
+        } else {
+            self
+        }
+    }
+}
@@ -0,0 +1,68 @@
+// src/spectrum.rs
+use rustfft::{num_complex::Complex, FftPlanner};
+
+/// One-sided power spectrum of a uniformly sampled signal as (frequency in Hz, power) pairs,
+/// from 0 up to the Nyquist frequency 1/(2 dt).
+/// The mean is removed first so the DC bin doesn't swamp the plot, and a Hann window
+/// keeps a peak that falls between bins from leaking across the whole spectrum.
+pub fn power_spectrum(samples: &[f64], dt: f64) -> Vec<(f64, f64)> {
+    let len = samples.len();
+    if len < 2 {
+        return Vec::new();
+    }
+    let mean = samples.iter().sum::<f64>() / len as f64;
+    let mut buffer: Vec<Complex<f64>> = samples
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| {
+            let hann = 0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / (len - 1) as f64).cos();
+            Complex::new((x - mean) * hann, 0.0)
+        })
+        .collect();
+    FftPlanner::new().plan_fft_forward(len).process(&mut buffer);
+
+    let df = 1.0 / (len as f64 * dt);
+    buffer[..=len / 2]
+        .iter()
+        .enumerate()
+        .map(|(k, c)| (k as f64 * df, c.norm_sqr() / len as f64))
+        .collect()
+}
+
+/// Frequency of the strongest bin, refined by fitting a parabola through it and its neighbours
+/// (in log power, which is exact for a Gaussian-shaped peak and close for a Hann-windowed one).
+pub fn peak_frequency(spectrum: &[(f64, f64)]) -> Option<f64> {
+    let (k, _) = spectrum
+        .iter()
+        .enumerate()
+        .skip(1) // DC is zero after mean removal
+        .max_by(|a, b| a.1 .1.total_cmp(&b.1 .1))?;
+    if k + 1 >= spectrum.len() {
+        return Some(spectrum[k].0);
+    }
+    let (a, b, c) = (spectrum[k - 1].1.ln(), spectrum[k].1.ln(), spectrum[k + 1].1.ln());
+    let denom = a - 2.0 * b + c;
+    let offset = if denom.abs() > 0.0 { 0.5 * (a - c) / denom } else { 0.0 };
+    let df = spectrum[1].0 - spectrum[0].0;
+    Some(spectrum[k].0 + offset * df)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::NPendulumSolver;
+    use crate::math::STANDARD_GRAVITY;
+
+    #[test]
+    fn peak_sits_at_the_small_angle_frequency() {
+        // m = 1 kg, L = 1 m released from rest at 1°, 100 s at dt = 0.01 against √(g/L)/2π. The bins are
+        // 0.01 Hz apart (2% of the ~0.5 Hz peak); the interpolation brings it well below that.
+        let solver = NPendulumSolver::new(1, vec![0.0, 1.0], vec![0.0, 1.0], STANDARD_GRAVITY);
+        let (_t, sol) = solver.solve(vec![0.0, 1.0_f64.to_radians()], vec![0.0, 0.0], 100.0, 10_001).expect("test chain is nonsingular");
+        let theta: Vec<f64> = sol.iter().map(|state| state[0]).collect();
+        let peak = peak_frequency(&power_spectrum(&theta, 0.01)).expect("the spectrum has a peak");
+        let expected = solver.g.sqrt() / (2.0 * std::f64::consts::PI);
+        let error = (peak - expected).abs() / expected;
+        assert!(error < 2e-3, "{:e}", error);
+    }
+}
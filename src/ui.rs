@@ -1,32 +1,623 @@
 // src/ui.rs
-use crate::logic::NPendulumSolver;
+use crate::circular::{self, CircularStats};
+use crate::export;
+use crate::logic::{angles_from_positions, check_mass_pattern, one_based, state_to_cartesian, AngleCondition, BobClearance, Comparison, Diverged, Forcing, Integrator, NPendulumSolver, OnDivergence, PdController, States, TorqueTable, STEPS_PER_PERIOD};
+use crate::math::{LinAlgError, STANDARD_GRAVITY};
+use crate::plot::{self, Arrow, ColorPalette, SeriesStyle, YAxis};
+use crate::verify;
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::web::Bytes;
 use actix_web::{web, HttpResponse, Result};
+use nalgebra::{DMatrix, DVector};
 use serde::{Deserialize, Serialize};
-use nalgebra::DVector;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 
 #[derive(Deserialize)]
 pub struct SimParams {
     n: usize,                // Number of pendulums
-    masses: String,          // Comma-separated masses
-    lengths: String,         // Comma-separated lengths
+    masses: String,          // Comma-separated masses, or one value for all links
+    lengths: String,         // Comma-separated lengths, or one value for all links
+    #[serde(default)]
     initial_angles: String,  // Comma-separated initial angles (degrees)
+    #[serde(default)]
+    angle_reference: AngleReference, // Vertical the initial angles are measured from
+    #[serde(default)]
+    initial_preset: Option<InitialPreset>, // Generated start instead of initial_angles, see `InitialPreset`
+    #[serde(default)]
+    seed: Option<u64>,       // Seed for the "random" preset, drawn fresh (and returned) when omitted
+    #[serde(default)]
+    initial_ang_vels: Option<InitialVelocities>, // rad/s list or rule; start from rest when omitted
+    #[serde(default, alias = "initial_ang_vels_deg")]
+    initial_velocities: Option<String>, // "ω1,ω2,...,ωn" (deg/s) instead of initial_ang_vels, also usable from a query string
     t_max: f64,              // Simulation duration
     n_points: usize,         // Resolution
+    #[serde(default)]
+    labels: Option<Vec<String>>, // Legend label per link (default "Pendulum k")
+    #[serde(default)]
+    colors: Option<Vec<String>>, // Hex color per link, e.g. "#1f77b4"
+    #[serde(default)]
+    palette: ColorPalette,       // "default", "colorblind" or "grayscale"; custom colors take precedence
+    #[serde(default)]
+    positions_format: PositionsFormat, // animation_data.positions as "cartesian" (default) or "angles"
+    #[serde(default)]
+    include_ang_vels: bool,      // With "angles", also return ω per step in animation_data.ang_vels
+    #[serde(default)]
+    layout: Layout,              // animation_data arrays per step ("row", default) or per variable ("column")
+    #[serde(default)]
+    plot_mode: PlotMode,         // Paths on the plot: "all" (default), "last" (recommended) or "list"
+    #[serde(default)]
+    plot_links: Vec<usize>,      // 1-based links drawn with plot_mode "list"
+    #[serde(default)]
+    line_width: Option<f64>,     // Trajectory width (px), default 1 with 2 for the outermost bob
+    #[serde(default = "default_line_opacity")]
+    line_opacity: f64,           // Trajectory opacity in [0, 1]
+    #[serde(default)]
+    plot_margin: Option<f64>,    // Padding around the chain as a fraction of its total length (default a fixed 0.5 m)
+    #[serde(default)]
+    simplify_tolerance: Option<f64>, // Simplify plotted paths to within this distance (m); animation_data is untouched
+    #[serde(default)]
+    draw_velocity_arrows: bool,  // Overlay each bob's final velocity on the plot
+    #[serde(default)]
+    draw_center_of_mass: bool,   // Overlay the path of the system's center of mass on the plot
+    #[serde(default = "default_arrow_scale")]
+    arrow_scale: f64,            // Arrow length per unit speed (s), i.e. meters per m/s
+    #[serde(default)]
+    energy_plot: bool,           // Also render E(t) with the relative drift band
+    #[serde(default = "default_supersample")]
+    supersample: u32,            // Trajectory plot antialiasing: drawn this many times larger, then averaged down
+    #[serde(default)]
+    integrator: Integrator,      // "rk4" (default), "rk2" or "heun"
+    #[serde(default = "default_substeps")]
+    substeps: usize,             // Integrator steps per recorded sample, each dt / substeps long
+    #[serde(default)]
+    nondimensional: bool,        // Integrate in units of the chain's own scales, see `NPendulumSolver::nondimensionalized`
+    #[serde(default)]
+    on_divergence: OnDivergence, // On a blow-up or an exhausted max_millis: "error" (default) or "truncate" to what was computed
+    #[serde(default = "default_max_millis")]
+    max_millis: u64,             // Wall-time budget for the integration (ms)
+    #[serde(default)]
+    damping: String,             // Comma-separated viscous joint damping b_k (N·m·s), or one value for all joints; "" for none
+    #[serde(default)]
+    gravity: Option<f64>,        // Gravitational acceleration (m/s²), default 9.81: 1.62 for the Moon, 0 for free fall
+    #[serde(default)]
+    gravity_rotation_rate: f64,  // Gravity vector turns at this rate (rad/s), 0 keeps it pointing down
+    #[serde(default)]
+    control_torques: Option<TorqueTable>, // {"times": [...], "torques": [[τ1, ..., τn], ...]} (s, N·m), interpolated linearly
+    #[serde(default)]
+    controller: Option<ControllerParams>, // Closed-loop PD torque at one joint, see `PdController`
+    #[serde(default)]
+    mount_tilt_deg: f64,         // Apparatus tilted by φ (degrees): gravity turns by φ and the chain hangs at θ = φ
+    #[serde(default)]
+    title: Option<String>,       // Trajectory plot caption, "" for none (default "Trajectories (n=N)")
+    #[serde(default)]
+    y_axis: YAxis,               // Plot orientation: "down" (default, chain hangs below the pivot) or "up" (flipped)
+}
+
+fn default_arrow_scale() -> f64 {
+    0.1
+}
+
+fn default_line_opacity() -> f64 {
+    0.75
+}
+
+/// Largest chain a request may ask for; masses and lengths can broadcast one value to all n links.
+const MAX_LINKS: usize = 100;
+
+const MAX_LINE_WIDTH: f64 = 20.0;
+
+/// Padding (m) added to the total length for the plot limits when no `plot_margin` is given.
+const DEFAULT_PLOT_PADDING: f64 = 0.5;
+
+const MAX_PLOT_MARGIN: f64 = 10.0;
+
+fn default_supersample() -> u32 {
+    2
+}
+
+const MAX_SUPERSAMPLE: u32 = 4;
+
+fn default_substeps() -> usize {
+    1
+}
+
+const MAX_SUBSTEPS: usize = 100;
+
+fn default_max_millis() -> u64 {
+    30_000
+}
+
+const MAX_MILLIS: u64 = 120_000;
+
+/// Integration steps between two clock reads of the `max_millis` budget.
+const BUDGET_CHECK_INTERVAL: usize = 256;
+
+/// Layout of `animation_data.positions`. The plots are always drawn from Cartesian positions.
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionsFormat {
+    #[default]
+    Cartesian, // [x1, y1, x2, y2, ...] per step (m)
+    Angles,    // [θ1, θ2, ..., θn] per step (rad), straight from the solver with no conversion
+}
+
+/// Nesting of the `animation_data` arrays.
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Layout {
+    #[default]
+    Row,    // [step][variable]: one row per time step, as the solver produces them
+    Column, // [variable][step]: one full time series per variable, e.g. positions[0] is x1 (or θ1) over time
+}
+
+/// Which bob paths the trajectory plot draws; `animation_data` always carries every link.
+/// "last" is the recommended choice for double and triple pendulums, whose outermost path is
+/// usually the interesting one; "all" stays the default so existing requests keep their plots.
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlotMode {
+    #[default]
+    All,  // Every link
+    Last, // Only link n
+    List, // The links in `plot_links`
+}
+
+/// Direction the supplied initial angles are measured from.
+/// Each link's angle is absolute (from the vertical, not from the previous link), so with `Up`
+/// every link k starts at θ_k = 180° + a_k, i.e. a_k = 0 for all links is the fully inverted chain.
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AngleReference {
+    #[default]
+    Down, // θ_k = a_k, 0° hangs straight down
+    Up,   // θ_k = 180° + a_k, 0° points straight up
+}
+
+/// Request form of `logic::PdController`, with the target in degrees like `initial_angles`.
+/// A single inverted pendulum is held up by {"kp": [30], "kd": [8], "target_deg": [180]}.
+#[derive(Deserialize)]
+pub struct ControllerParams {
+    #[serde(default = "default_link")]
+    link: usize,           // Actuated joint, 1-based
+    kp: Vec<f64>,          // Proportional gain per link (N·m/rad)
+    kd: Vec<f64>,          // Derivative gain per link (N·m·s/rad)
+    target_deg: Vec<f64>,  // Target angle per link (degrees), compared unwrapped
+}
+
+/// Generated initial angles for common demos, used in place of `initial_angles`.
+/// The angles are absolute, so `angle_reference` does not apply to them.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InitialPreset {
+    Horizontal, // Every θ_k = 90°, the straight chain sticks out to the right and collapses
+    VerticalUp, // Every θ_k = 180°, balanced straight up
+    Random,     // Every θ_k uniform in [-180°, 180°) from the seeded RNG
+}
+
+impl InitialPreset {
+    /// The zero-indexed angles [θ1, ..., θn] (rad), with the seed used by `Random`.
+    fn angles(self, n: usize, seed: Option<u64>) -> (Vec<f64>, Option<u64>) {
+        use std::f64::consts::PI;
+        match self {
+            InitialPreset::Horizontal => (vec![PI / 2.0; n], None),
+            InitialPreset::VerticalUp => (vec![PI; n], None),
+            InitialPreset::Random => {
+                let (mut rng, seed) = seeded_rng(seed);
+                ((0..n).map(|_| rng.random_range(-PI..PI)).collect(), Some(seed))
+            }
+        }
+    }
+}
+
+/// Initial angular velocities, either listed per link or generated from a rule.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum InitialVelocities {
+    List(Vec<f64>), // [ω1, ω2, ..., ωn] (rad/s)
+    Spec(VelocitySpec),
+}
+
+/// Rules for structured initial velocities (rad/s).
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum VelocitySpec {
+    Gradient { start: f64, end: f64 }, // Linear from ω1 = start to ωn = end
+    Impulse { index: usize, value: f64 }, // ω_index = value (1-based), all others zero
+    // Speed v_k (m/s) of each bob relative to the previous one, perpendicular to its rod: ω_k = v_k / L_k.
+    // Equal ω_k on a straight chain without gravity spins it rigidly at constant speed.
+    Tangential { speeds: Vec<f64> },
+}
+
+impl InitialVelocities {
+    /// Expands into the zero-indexed velocity vector [ω1, ..., ωn] for a chain with these
+    /// (zero-indexed) link lengths.
+    fn to_vec(&self, lengths: &[f64]) -> std::result::Result<Vec<f64>, ParamError> {
+        let n = lengths.len();
+        let omegas = match self {
+            InitialVelocities::List(list) => list.clone(),
+            InitialVelocities::Spec(VelocitySpec::Gradient { start, end }) => (0..n)
+                .map(|k| if n > 1 { start + (end - start) * k as f64 / (n - 1) as f64 } else { *start })
+                .collect(),
+            InitialVelocities::Spec(VelocitySpec::Impulse { index, value }) => {
+                if !(1..=n).contains(index) {
+                    return Err(ParamError::ImpulseIndex { n, index: *index });
+                }
+                let mut omegas = vec![0.0; n];
+                omegas[index - 1] = *value;
+                omegas
+            }
+            InitialVelocities::Spec(VelocitySpec::Tangential { speeds }) => {
+                if speeds.len() != n {
+                    return Err(ParamError::VelocityCount { expected: n, got: speeds.len() });
+                }
+                speeds.iter().zip(lengths).map(|(v, l)| v / l).collect()
+            }
+        };
+        if omegas.len() != n {
+            return Err(ParamError::VelocityCount { expected: n, got: omegas.len() });
+        }
+        if omegas.iter().any(|w| !w.is_finite()) {
+            return Err(ParamError::NonFiniteVelocity);
+        }
+        Ok(omegas)
+    }
 }
 
 #[derive(Serialize)]
 struct SimResponse {
     success: bool,
+    completed: bool, // false if the trajectory stops before t_max
+    t_reached: f64,  // Time of the last returned sample (s)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diverged_at: Option<f64>, // Time the state blew up, set when the trajectory was truncated there
     animation_data: AnimationData,
     #[serde(skip_serializing_if = "Option::is_none")]
+    plot_image: Option<String>, // Base64 PNG of the trajectories
+    #[serde(skip_serializing_if = "Option::is_none")]
+    energy_plot_image: Option<String>, // Base64 PNG of E(t) and (E - E0)/|E0|, when requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diagnostics: Option<Diagnostics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trajectory_hash: Option<String>, // BLAKE3 of the solution for cache validation, needs the `hash` feature
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>, // Seed of the "random" initial preset, to replay the run
+    #[serde(skip_serializing_if = "Option::is_none")]
     message: Option<String>,
 }
 
+/// Circular summary of a set of angles: the mean direction and how tightly they cluster around it.
+#[derive(Serialize)]
+struct AngleStats {
+    mean: Option<f64>,    // Mean direction (degrees, from straight down), null when the angles cancel out
+    order_parameter: f64, // Resultant length R in [0, 1]; the circular variance is 1 - R
+}
+
+impl From<CircularStats> for AngleStats {
+    fn from(stats: CircularStats) -> Self {
+        AngleStats {
+            mean: stats.mean.map(f64::to_degrees),
+            order_parameter: stats.resultant_length,
+        }
+    }
+}
+
+/// Cheap sanity checks computed over the returned trajectory.
+#[derive(Serialize, Default)]
+struct Diagnostics {
+    max_rod_length_error: f64, // max | |pos_k - pos_(k-1)| - L_k |, ~1e-15 for rigid links
+    arc_lengths: Vec<f64>,     // Path length traced by each bob (m)
+    max_rod_tensions: Vec<f64>, // Largest axial force per rod (N), negative if it only ever compresses
+    mean_positions: Vec<(f64, f64)>, // Time-averaged (x̄, ȳ) of each bob (m)
+    mean_angles: Vec<AngleStats>,    // Time-averaged direction of each link, averaged on the circle
+    min_bob_clearance: Option<BobClearance>, // Closest approach of two non-adjacent bobs, null below n = 3
+}
+
 #[derive(Serialize, Default)]
 struct AnimationData {
-    positions: Vec<Vec<f64>>, // Flattened [x1, y1, x2, y2...] per time step
+    positions: Vec<Vec<f64>>, // Flattened [x1, y1, x2, y2...] per time step, or [θ1, ..., θn] for "angles"; transposed for "column"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ang_vels: Option<Vec<Vec<f64>>>, // [ω1, ..., ωn] per time step, with "angles" and include_ang_vels; transposed for "column"
     n: usize,
-    limit: f64,               // Boundary for frontend scaling
+    limit: f64,               // Boundary for frontend scaling; it bounds Cartesian positions and means nothing for angles
+}
+
+/// Everything that can be wrong with a `SimParams` request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamError {
+    NoPendulums,
+    TooManyPendulums(usize),
+    TooFewPoints(usize),
+    LengthMismatch { expected: usize, masses: usize, lengths: usize, angles: usize },
+    LabelCount { expected: usize, got: usize },
+    InvalidColor(String),
+    ColorCount { expected: usize, got: usize },
+    PresetWithAngles, // initial_preset and initial_angles both given
+    VelocitiesWithDegrees, // initial_ang_vels and initial_velocities both given
+    ArrowScale(f64),
+    Supersample(u32),
+    LineWidth(f64),
+    LineOpacity(f64),
+    SimplifyTolerance(f64),
+    PlotMargin(f64),
+    Substeps(usize),
+    MaxMillis(u64),
+    DampingCount { expected: usize, got: usize },
+    Damping(f64),
+    Gravity(f64),
+    GravityRotationRate(f64),
+    MountTilt(f64),
+    ControlTorques(String), // Rejected by `TorqueTable::check`
+    Controller(String),     // Rejected by `PdController::check`
+    VelocityCount { expected: usize, got: usize },
+    ImpulseIndex { n: usize, index: usize },
+    NonFiniteVelocity,
+    PlotLinks { n: usize, links: Vec<usize> }, // Empty or out-of-range list for plot_mode "list"
+    Masses(String),   // Rejected by `logic::check_mass_pattern`
+    TimeSpan(String), // Rejected by `NPendulumSolver::check_time_span`
+    Diverged(Diverged), // The run itself blew up and on_divergence is "error"
+    Singular(LinAlgError), // The mass matrix became singular during the run
+    TimeBudget { max_millis: u64, t: f64 }, // The run hit max_millis at time t and on_divergence is "error"
+}
+
+impl std::fmt::Display for ParamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamError::NoPendulums => write!(f, "At least one pendulum is required"),
+            ParamError::TooManyPendulums(n) => write!(f, "n must be at most {}, got {}", MAX_LINKS, n),
+            ParamError::TooFewPoints(got) => write!(f, "n_points must be at least 2, got {}", got),
+            ParamError::LengthMismatch { expected, masses, lengths, angles } => write!(
+                f,
+                "Input length mismatch. Expected {} (masses and lengths may also be a single value), got M:{}, L:{}, A:{}",
+                expected, masses, lengths, angles
+            ),
+            ParamError::LabelCount { expected, got } => write!(f, "Expected {} labels, got {}", expected, got),
+            ParamError::InvalidColor(c) => write!(f, "Invalid color '{}', expected #rrggbb", c),
+            ParamError::ColorCount { expected, got } => write!(f, "Expected {} colors, got {}", expected, got),
+            ParamError::PresetWithAngles => write!(f, "Give either initial_preset or initial_angles, not both"),
+            ParamError::VelocitiesWithDegrees => {
+                write!(f, "Give either initial_ang_vels (rad/s) or initial_velocities (deg/s), not both")
+            }
+            ParamError::ArrowScale(scale) => write!(f, "arrow_scale must be positive, got {}", scale),
+            ParamError::Supersample(got) => {
+                write!(f, "supersample must be between 1 and {}, got {}", MAX_SUPERSAMPLE, got)
+            }
+            ParamError::LineWidth(got) => {
+                write!(f, "line_width must be positive and at most {}, got {}", MAX_LINE_WIDTH, got)
+            }
+            ParamError::LineOpacity(got) => write!(f, "line_opacity must be between 0 and 1, got {}", got),
+            ParamError::PlotMargin(got) => {
+                write!(f, "plot_margin must be between 0 and {}, got {}", MAX_PLOT_MARGIN, got)
+            }
+            ParamError::SimplifyTolerance(got) => {
+                write!(f, "simplify_tolerance must be positive and finite, got {}", got)
+            }
+            ParamError::Substeps(got) => write!(f, "substeps must be between 1 and {}, got {}", MAX_SUBSTEPS, got),
+            ParamError::MaxMillis(got) => write!(f, "max_millis must be between 1 and {}, got {}", MAX_MILLIS, got),
+            ParamError::DampingCount { expected, got } => {
+                write!(f, "Expected {} damping coefficients (or a single value), got {}", expected, got)
+            }
+            ParamError::Damping(b) => write!(f, "damping must be non-negative and finite, got {}", b),
+            ParamError::Gravity(g) => write!(f, "gravity must be finite and non-negative, got {}", g),
+            ParamError::GravityRotationRate(rate) => {
+                write!(f, "gravity_rotation_rate must be finite, got {}", rate)
+            }
+            ParamError::ControlTorques(message) => write!(f, "{}", message),
+            ParamError::Controller(message) => write!(f, "{}", message),
+            ParamError::MountTilt(tilt) => write!(f, "mount_tilt_deg must be between -180 and 180, got {}", tilt),
+            ParamError::VelocityCount { expected, got } => {
+                write!(f, "Expected {} initial angular velocities, got {}", expected, got)
+            }
+            ParamError::ImpulseIndex { n, index } => {
+                write!(f, "Impulse index must be between 1 and {}, got {}", n, index)
+            }
+            ParamError::NonFiniteVelocity => write!(f, "Initial angular velocities must be finite"),
+            ParamError::PlotLinks { n, links } => {
+                write!(f, "plot_mode \"list\" needs plot_links between 1 and {}, got {:?}", n, links)
+            }
+            ParamError::Masses(message) => write!(f, "{}", message),
+            ParamError::TimeSpan(message) => write!(f, "{}", message),
+            ParamError::Diverged(e) => write!(f, "{}", e),
+            ParamError::Singular(e) => write!(f, "{}", e),
+            ParamError::TimeBudget { max_millis, t } => {
+                write!(f, "Time budget of {} ms exceeded at t = {}", max_millis, t)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParamError {}
+
+/// `SimParams` after parsing and validation. Vectors are zero-indexed, one entry per link.
+pub struct ValidatedParams {
+    pub n: usize,
+    pub masses: Vec<f64>,
+    pub lengths: Vec<f64>,
+    pub initial_angles: Vec<f64>,   // Radians
+    pub initial_ang_vels: Vec<f64>, // rad/s
+    pub damping: Vec<f64>,          // N·m·s per joint, zero when not given
+    pub t_max: f64,
+    pub n_points: usize,
+    pub styles: Vec<SeriesStyle>,
+    pub shown: Vec<bool>,         // Per link, whether the plot draws its path
+    pub arrow_scale: Option<f64>, // Set when velocity arrows were requested
+    pub supersample: u32,
+    pub seed: Option<u64>,        // Seed the "random" initial preset used
+    pub limit: f64,               // Half-width of the plot area (m): total length plus padding
+    pub controller: Option<PdController>,
+}
+
+impl TryFrom<&SimParams> for ValidatedParams {
+    type Error = ParamError;
+
+    fn try_from(params: &SimParams) -> std::result::Result<Self, ParamError> {
+        let n = params.n;
+        // Checked before anything is sized by n
+        if n > MAX_LINKS {
+            return Err(ParamError::TooManyPendulums(n));
+        }
+
+        // 1. Parse Inputs
+        let masses = broadcast(parse_csv_f64(&params.masses), n);
+        let lengths = broadcast(parse_csv_f64(&params.lengths), n);
+        let (initial_angles, seed) = match params.initial_preset {
+            None => {
+                let angles = parse_csv_f64(&params.initial_angles)
+                    .iter()
+                    .map(|d| match params.angle_reference {
+                        AngleReference::Down => d.to_radians(),
+                        AngleReference::Up => (180.0 + d).to_radians(),
+                    })
+                    .collect();
+                (angles, None)
+            }
+            Some(_) if !params.initial_angles.trim().is_empty() => return Err(ParamError::PresetWithAngles),
+            Some(preset) => preset.angles(n, params.seed),
+        };
+
+        // 2. Validate Inputs
+        if n == 0 {
+            return Err(ParamError::NoPendulums);
+        }
+        if params.n_points < 2 {
+            return Err(ParamError::TooFewPoints(params.n_points));
+        }
+        if masses.len() != n || lengths.len() != n || initial_angles.len() != n {
+            return Err(ParamError::LengthMismatch {
+                expected: n,
+                masses: masses.len(),
+                lengths: lengths.len(),
+                angles: initial_angles.len(),
+            });
+        }
+        check_mass_pattern(&one_based(&masses)).map_err(ParamError::Masses)?;
+        let damping = match params.damping.trim() {
+            "" => vec![0.0; n],
+            list => broadcast(parse_csv_f64(list), n),
+        };
+        if damping.len() != n {
+            return Err(ParamError::DampingCount { expected: n, got: damping.len() });
+        }
+        if let Some(&b) = damping.iter().find(|b| !(b.is_finite() && **b >= 0.0)) {
+            return Err(ParamError::Damping(b));
+        }
+
+        let labels = params.labels.clone().unwrap_or_default();
+        if !labels.is_empty() && labels.len() != n {
+            return Err(ParamError::LabelCount { expected: n, got: labels.len() });
+        }
+        let colors = params
+            .colors
+            .iter()
+            .flatten()
+            .map(|c| plot::parse_hex_color(c).ok_or_else(|| ParamError::InvalidColor(c.clone())))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        if !colors.is_empty() && colors.len() != n {
+            return Err(ParamError::ColorCount { expected: n, got: colors.len() });
+        }
+        let shown = match params.plot_mode {
+            PlotMode::All => vec![true; n],
+            PlotMode::Last => (1..=n).map(|k| k == n).collect(),
+            PlotMode::List => {
+                if params.plot_links.is_empty() || params.plot_links.iter().any(|k| !(1..=n).contains(k)) {
+                    return Err(ParamError::PlotLinks { n, links: params.plot_links.clone() });
+                }
+                (1..=n).map(|k| params.plot_links.contains(&k)).collect()
+            }
+        };
+        if params.draw_velocity_arrows && (!params.arrow_scale.is_finite() || params.arrow_scale <= 0.0) {
+            return Err(ParamError::ArrowScale(params.arrow_scale));
+        }
+        if !(1..=MAX_SUPERSAMPLE).contains(&params.supersample) {
+            return Err(ParamError::Supersample(params.supersample));
+        }
+        if let Some(w) = params.line_width {
+            if !(w > 0.0 && w <= MAX_LINE_WIDTH) {
+                return Err(ParamError::LineWidth(w));
+            }
+        }
+        if !(0.0..=1.0).contains(&params.line_opacity) {
+            return Err(ParamError::LineOpacity(params.line_opacity));
+        }
+        let total_length: f64 = lengths.iter().sum();
+        let limit = match params.plot_margin {
+            None => total_length + DEFAULT_PLOT_PADDING,
+            Some(margin) if (0.0..=MAX_PLOT_MARGIN).contains(&margin) => total_length * (1.0 + margin),
+            Some(margin) => return Err(ParamError::PlotMargin(margin)),
+        };
+        if let Some(tol) = params.simplify_tolerance {
+            if !(tol.is_finite() && tol > 0.0) {
+                return Err(ParamError::SimplifyTolerance(tol));
+            }
+        }
+        if !(1..=MAX_SUBSTEPS).contains(&params.substeps) {
+            return Err(ParamError::Substeps(params.substeps));
+        }
+        if !(1..=MAX_MILLIS).contains(&params.max_millis) {
+            return Err(ParamError::MaxMillis(params.max_millis));
+        }
+        if let Some(g) = params.gravity.filter(|g| !(g.is_finite() && *g >= 0.0)) {
+            return Err(ParamError::Gravity(g));
+        }
+        if !params.gravity_rotation_rate.is_finite() {
+            return Err(ParamError::GravityRotationRate(params.gravity_rotation_rate));
+        }
+        if !(-180.0..=180.0).contains(&params.mount_tilt_deg) {
+            return Err(ParamError::MountTilt(params.mount_tilt_deg));
+        }
+        if let Some(table) = &params.control_torques {
+            table.check(n).map_err(ParamError::ControlTorques)?;
+        }
+        let controller = params.controller.as_ref().map(|c| PdController {
+            link: c.link,
+            kp: c.kp.clone(),
+            kd: c.kd.clone(),
+            target: c.target_deg.iter().map(|d| d.to_radians()).collect(),
+        });
+        if let Some(c) = &controller {
+            c.check(n).map_err(ParamError::Controller)?;
+        }
+
+        let initial_ang_vels = match (&params.initial_ang_vels, &params.initial_velocities) {
+            (Some(_), Some(_)) => return Err(ParamError::VelocitiesWithDegrees),
+            (Some(spec), None) => spec.to_vec(&lengths)?,
+            (None, Some(degrees)) if !degrees.trim().is_empty() => {
+                let rad = parse_csv_f64(degrees).iter().map(|w| w.to_radians()).collect();
+                InitialVelocities::List(rad).to_vec(&lengths)?
+            }
+            _ => vec![0.0; n], // Start from rest
+        };
+
+        Ok(ValidatedParams {
+            n,
+            masses,
+            lengths,
+            initial_angles,
+            initial_ang_vels,
+            damping,
+            t_max: params.t_max,
+            n_points: params.n_points,
+            styles: plot::series_styles(n, params.palette, &colors, &labels),
+            shown,
+            arrow_scale: params.draw_velocity_arrows.then_some(params.arrow_scale),
+            supersample: params.supersample,
+            seed,
+            limit,
+            controller,
+        })
+    }
+}
+
+impl TryFrom<SimParams> for ValidatedParams {
+    type Error = ParamError;
+
+    fn try_from(params: SimParams) -> std::result::Result<Self, ParamError> {
+        ValidatedParams::try_from(&params)
+    }
 }
 
 /// Helper: Parses a comma-separated string into a Vec<f64>.
@@ -36,93 +627,2559 @@ fn parse_csv_f64(s: &str) -> Vec<f64> {
         .collect()
 }
 
-/// Helper: Converts angular states (theta) into Cartesian coordinates (x, y).
-/// Returns a vector of time steps, where each step is [x1, y1, x2, y2, ...].
-fn compute_positions(sol: &[DVector<f64>], n: usize, lengths: &[f64]) -> Vec<Vec<f64>> {
-    let mut positions = Vec::with_capacity(sol.len());
+/// Helper: Expands a single value into `n` equal entries ("all equal" shorthand).
+/// Any other count is returned unchanged for the length check to report.
+fn broadcast(values: Vec<f64>, n: usize) -> Vec<f64> {
+    match values.as_slice() {
+        &[v] => vec![v; n],
+        _ => values,
+    }
+}
+
+/// Helper: Seeds the RNG from the request, or draws a fresh seed so the run can be replayed.
+/// Returns the generator together with the seed that was actually used.
+fn seeded_rng(seed: Option<u64>) -> (StdRng, u64) {
+    let seed = seed.unwrap_or_else(rand::random);
+    (StdRng::seed_from_u64(seed), seed)
+}
+
+/// Parsed, 1-indexed physics inputs shared by the handlers.
+struct PreparedSim {
+    solver: NPendulumSolver,
+    initial_angles: Vec<f64>,
+    initial_ang_vels: Vec<f64>,
+    styles: Vec<SeriesStyle>,
+    shown: Vec<bool>,
+    arrow_scale: Option<f64>,
+    supersample: u32,
+    seed: Option<u64>,
+    limit: f64,
+}
+
+/// Helper: Validates `SimParams` and returns a ready-to-run solver.
+fn prepare_sim(params: &SimParams) -> std::result::Result<PreparedSim, ParamError> {
+    let valid = ValidatedParams::try_from(params)?;
+
+    // 3. Initialize Solver
+    let g = params.gravity.unwrap_or(STANDARD_GRAVITY);
+    let mut solver = NPendulumSolver::new(valid.n, one_based(&valid.masses), one_based(&valid.lengths), g);
+    solver.integrator = params.integrator;
+    solver.substeps = params.substeps;
+    solver.damping = one_based(&valid.damping);
+    solver.gravity_rotation_rate = params.gravity_rotation_rate;
+    solver.gravity_tilt = params.mount_tilt_deg.to_radians();
+    solver.control_torques = params.control_torques.clone();
+    solver.controller = valid.controller;
+    solver.check_time_span(valid.t_max).map_err(ParamError::TimeSpan)?;
+
+    Ok(PreparedSim {
+        solver,
+        initial_angles: one_based(&valid.initial_angles),
+        initial_ang_vels: one_based(&valid.initial_ang_vels),
+        styles: valid.styles,
+        shown: valid.shown,
+        arrow_scale: valid.arrow_scale,
+        supersample: valid.supersample,
+        seed: valid.seed,
+        limit: valid.limit,
+    })
+}
+
+/// Result of one validated simulation run, independent of how it is delivered (HTTP or CLI).
+pub struct SimRun {
+    pub solver: NPendulumSolver,
+    pub t: Vec<f64>,
+    pub sol: Vec<DVector<f64>>,
+    pub positions: Vec<Vec<f64>>, // [x1, y1, x2, y2, ...] per time step
+    pub limit: f64,               // Boundary for plot / frontend scaling
+    pub styles: Vec<SeriesStyle>,
+    pub shown: Vec<bool>,         // Per link, whether the plot draws its path (see `PlotMode`)
+    pub line_width: Option<f64>,  // Trajectory width (px), None for the 1 / 2 px default
+    pub line_opacity: f64,
+    pub simplify_tolerance: Option<f64>, // RDP tolerance for the plotted paths (m)
+    pub arrows: Vec<Arrow>,       // Final-state velocity arrows, empty unless requested
+    pub center_of_mass: Vec<(f64, f64)>, // Center-of-mass path, empty unless requested
+    pub supersample: u32,         // Antialiasing factor for the trajectory plot
+    pub seed: Option<u64>,        // Seed of the "random" initial preset
+    pub completed: bool,          // false if the run was stopped before t_max, see `run_simulation_until`
+    pub diverged_at: Option<f64>, // When the state blew up, if the run was truncated there
+    pub out_of_time: bool,        // The max_millis budget ran out and the run was truncated
+    pub caption: String,          // Trajectory plot caption, empty for none
+    pub y_axis: YAxis,            // Plot orientation; positions stay in physics coordinates
+}
+
+impl SimRun {
+    /// The trajectory plot of this run.
+    pub fn plot(&self) -> plot::TrajectoryPlot<'_> {
+        plot::TrajectoryPlot {
+            positions: &self.positions,
+            n: self.solver.n,
+            limit: self.limit,
+            styles: &self.styles,
+            shown: &self.shown,
+            line_width: self.line_width,
+            line_opacity: self.line_opacity,
+            simplify_tolerance: self.simplify_tolerance,
+            arrows: &self.arrows,
+            center_of_mass: &self.center_of_mass,
+            caption: &self.caption,
+            y_axis: self.y_axis,
+        }
+    }
+}
+
+/// Parses, validates and solves `params` without touching any transport.
+pub fn run_simulation(params: &SimParams) -> std::result::Result<SimRun, ParamError> {
+    run_simulation_until(params, |_, _| false)
+}
+
+/// `run_simulation` that asks `should_stop(t, state)` before every step. A stopped run still
+/// returns the samples computed so far, with `completed` set to false.
+/// The integration also stops once `max_millis` have passed; the clock is read every
+/// `BUDGET_CHECK_INTERVAL` steps, so the overshoot is at most that many steps.
+pub fn run_simulation_until<F>(params: &SimParams, should_stop: F) -> std::result::Result<SimRun, ParamError>
+where
+    F: FnMut(f64, &DVector<f64>) -> bool,
+{
+    run_prepared(params, prepare_sim(params)?, should_stop)
+}
+
+/// `run_simulation_until` for a `prepare_sim` result the handler has checked or adjusted further,
+/// under the same max_millis budget, `on_divergence` and `nondimensional` handling.
+fn run_prepared<F>(params: &SimParams, prepared: PreparedSim, should_stop: F) -> std::result::Result<SimRun, ParamError>
+where
+    F: FnMut(f64, &DVector<f64>) -> bool,
+{
+    run_prepared_within(params, prepared, budget_deadline(params), should_stop)
+}
+
+/// `run_prepared` against a `deadline` the caller shares between several runs.
+fn run_prepared_within<F>(
+    params: &SimParams,
+    prepared: PreparedSim,
+    deadline: Instant,
+    should_stop: F,
+) -> std::result::Result<SimRun, ParamError>
+where
+    F: FnMut(f64, &DVector<f64>) -> bool,
+{
+    // 4. Run Simulation
+    let Integration { t, sol, completed, diverged_at, out_of_time } = integrate(
+        params,
+        &prepared.solver,
+        prepared.initial_angles,
+        prepared.initial_ang_vels,
+        deadline,
+        should_stop,
+    )?;
+
+    // 5. Post-Process Results
+    // Display limit (total length + padding, see `plot_margin`)
+    let limit = prepared.limit;
+
+    // Convert angles to Cartesian coordinates for the frontend
+    let positions = prepared.solver.cartesian_positions(&t, &sol);
+
+    let arrows = match (t.last().zip(sol.last()), prepared.arrow_scale) {
+        (Some((&t_last, last)), Some(scale)) => plot::velocity_arrows(
+            &prepared.solver.bob_positions(t_last, last),
+            &prepared.solver.cartesian_velocities(t_last, last),
+            scale,
+        ),
+        _ => Vec::new(),
+    };
+
+    let center_of_mass = if params.draw_center_of_mass {
+        prepared.solver.center_of_mass_series(&t, &sol)
+    } else {
+        Vec::new()
+    };
 
-    for state in sol {
-        let mut step_coords = Vec::with_capacity(2 * n);
-        let mut curr_x = 0.0;
-        let mut curr_y = 0.0;
+    Ok(SimRun {
+        solver: prepared.solver,
+        t,
+        sol,
+        positions,
+        limit,
+        styles: prepared.styles,
+        shown: prepared.shown,
+        line_width: params.line_width,
+        line_opacity: params.line_opacity,
+        simplify_tolerance: params.simplify_tolerance,
+        arrows,
+        center_of_mass,
+        supersample: prepared.supersample,
+        seed: prepared.seed,
+        completed,
+        diverged_at,
+        out_of_time,
+        caption: params.title.clone().unwrap_or_else(|| format!("Trajectories (n={})", params.n)),
+        y_axis: params.y_axis,
+    })
+}
 
-        // state contains [theta_1 ... theta_n, omega_1 ... omega_n]
-        // logic.rs uses 1-based indexing for lengths (index 0 is dummy)
-        // state vector from nalgebra is 0-indexed: state[0] is theta_1
-        for k in 0..n {
-            let theta = state[k]; // theta_(k+1)
-            let len = lengths[k + 1]; // L_(k+1)
+/// Samples of one `integrate` call.
+struct Integration {
+    t: Vec<f64>,
+    sol: Vec<DVector<f64>>,
+    completed: bool,
+    diverged_at: Option<f64>,
+    out_of_time: bool,
+}
 
-            curr_x += len * theta.sin();
-            curr_y -= len * theta.cos();
+/// When the `max_millis` budget of a request starts now runs out.
+fn budget_deadline(params: &SimParams) -> Instant {
+    Instant::now() + Duration::from_millis(params.max_millis)
+}
 
-            step_coords.push(curr_x);
-            step_coords.push(curr_y);
+/// The solve behind `run_simulation_until`, for handlers that adjust the prepared solver or start
+/// from several initial states: integrates `solver` from (angles, ang_vels) over `params`' t_max and
+/// n_points, honoring `nondimensional` and `on_divergence`, and stops once `deadline` has passed.
+/// Runs sharing one deadline share one budget. A singular mass matrix, a blow-up or an exhausted
+/// budget with on_divergence "error" is an error; with "truncate" the samples so far come back.
+fn integrate<F>(
+    params: &SimParams,
+    solver: &NPendulumSolver,
+    angles: Vec<f64>,
+    ang_vels: Vec<f64>,
+    deadline: Instant,
+    mut should_stop: F,
+) -> std::result::Result<Integration, ParamError>
+where
+    F: FnMut(f64, &DVector<f64>) -> bool,
+{
+    // should_stop runs once per sample, and each sample takes `substeps` integrator steps
+    let (mut steps, mut next_check) = (0usize, BUDGET_CHECK_INTERVAL);
+    let mut out_of_time = false;
+    let should_stop = |t: f64, y: &DVector<f64>| {
+        steps += solver.substeps;
+        if steps >= next_check {
+            next_check = steps + BUDGET_CHECK_INTERVAL;
+            if Instant::now() >= deadline {
+                out_of_time = true;
+                return true;
+            }
         }
-        positions.push(step_coords);
+        should_stop(t, y)
+    };
+    let (t_max, n_points, on_divergence) = (params.t_max, params.n_points, params.on_divergence);
+    let (run, diverged_at) = if params.nondimensional {
+        solver.solve_finite_nondimensional(angles, ang_vels, t_max, n_points, on_divergence, should_stop)
+    } else {
+        solver.solve_finite(angles, ang_vels, t_max, n_points, on_divergence, should_stop)
+    }
+    .map_err(ParamError::Diverged)?;
+    if let Some(e) = run.error {
+        return Err(ParamError::Singular(e));
     }
-    positions
+    if out_of_time && params.on_divergence == OnDivergence::Error {
+        return Err(ParamError::TimeBudget {
+            max_millis: params.max_millis,
+            t: run.t_reached(),
+        });
+    }
+    Ok(Integration {
+        t: run.t,
+        sol: run.sol,
+        completed: run.completed,
+        diverged_at,
+        out_of_time,
+    })
 }
 
 /// Main Handler: Orchestrates parsing, solving, and response formatting.
 pub async fn simulate_handler(params: web::Json<SimParams>) -> Result<HttpResponse> {
-    // 1. Parse Inputs
-    let masses = parse_csv_f64(&params.masses);
-    let lengths = parse_csv_f64(&params.lengths);
-    let angles_deg = parse_csv_f64(&params.initial_angles);
+    Ok(HttpResponse::Ok().json(simulate(&params)))
+}
 
-    // 2. Validate Inputs
-    if masses.len() != params.n || lengths.len() != params.n || angles_deg.len() != params.n {
-        return Ok(HttpResponse::Ok().json(SimResponse {
-            success: false,
-            animation_data: AnimationData::default(),
-            message: Some(format!(
-                "Input length mismatch. Expected {}, got M:{}, L:{}, A:{}",
-                params.n, masses.len(), lengths.len(), angles_deg.len()
-            )),
-        }));
+/// Helper: A failed `SimResponse` carrying only the message.
+fn sim_failure(message: String) -> SimResponse {
+    SimResponse {
+        success: false,
+        completed: false,
+        t_reached: 0.0,
+        diverged_at: None,
+        animation_data: AnimationData::default(),
+        plot_image: None,
+        energy_plot_image: None,
+        diagnostics: None,
+        trajectory_hash: None,
+        seed: None,
+        message: Some(message),
+    }
+}
+
+/// GeoJSON Handler: Runs the simulation and returns each bob's path as a LineString feature.
+pub async fn geojson_handler(params: web::Json<SimParams>) -> Result<HttpResponse> {
+    match run_simulation(&params) {
+        Ok(run) => Ok(HttpResponse::Ok()
+            .content_type("application/geo+json")
+            .body(export::trajectory_geojson(&run.positions, run.solver.n))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(sim_failure(e.to_string()))),
     }
+}
+
+/// CSV Handler: Runs the simulation and returns the positions as a `t,x1,y1,...` CSV download,
+/// the same file the `npend` command line writes.
+pub async fn csv_handler(params: web::Json<SimParams>) -> Result<HttpResponse> {
+    match run_simulation(&params) {
+        Ok(run) => Ok(HttpResponse::Ok()
+            .content_type("text/csv")
+            .insert_header(("Content-Disposition", "attachment; filename=\"trajectory.csv\""))
+            .body(export::trajectory_csv(&run.t, &run.positions, run.solver.n))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(sim_failure(e.to_string()))),
+    }
+}
 
-    // 3. Prepare Physics Vectors (1-based indexing padding)
-    // We prepend 0.0 because the physics logic (math.rs) expects 1-based indices [dummy, m1, m2...]
-    let mut full_masses = vec![0.0];
-    full_masses.extend(&masses);
+/// Arrays Handler: Runs the simulation and returns `t`, `theta`, `omega` and `xy` as rectangular arrays
+/// for NumPy and Matplotlib, see `export::TrajectoryArrays` for the schema.
+pub async fn arrays_handler(params: web::Json<SimParams>) -> Result<HttpResponse> {
+    match run_simulation(&params) {
+        Ok(run) => Ok(HttpResponse::Ok().json(export::trajectory_arrays(&run.t, &run.sol, &run.positions, run.solver.n))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(sim_failure(e.to_string()))),
+    }
+}
 
-    let mut full_lengths = vec![0.0];
-    full_lengths.extend(&lengths);
+/// NDJSON Handler: Streams the trajectory as one `{"t": ..., "positions": [x1, y1, ...]}` line per sample,
+/// each integrated only when the client is ready to read it, so memory stays flat for any n_points.
+/// `on_divergence` and `nondimensional` don't apply: a blow-up or an exhausted `max_millis` ends the
+/// stream with a last `{"error": "..."}` line. Invalid parameters still get a plain JSON error.
+pub async fn ndjson_handler(params: web::Json<SimParams>) -> Result<HttpResponse> {
+    let prepared = match prepare_sim(&params) {
+        Ok(p) => p,
+        Err(e) => return Ok(HttpResponse::BadRequest().json(sim_failure(e.to_string()))),
+    };
+    let geometry = prepared.solver.clone();
+    let states = prepared.solver.into_states(
+        prepared.initial_angles,
+        prepared.initial_ang_vels,
+        params.t_max,
+        params.n_points,
+    );
+    Ok(HttpResponse::Ok().content_type("application/x-ndjson").body(NdjsonFrames {
+        states,
+        geometry,
+        deadline: Instant::now() + Duration::from_millis(params.max_millis),
+        max_millis: params.max_millis,
+        done: false,
+    }))
+}
 
-    let mut full_angles = vec![0.0];
-    full_angles.extend(angles_deg.iter().map(|d| d.to_radians()));
+/// One line of /ndjson.
+#[derive(Serialize)]
+struct NdjsonFrame<'a> {
+    t: f64,
+    positions: &'a [f64], // [x1, y1, x2, y2, ...] (m)
+}
 
-    let initial_ang_vels = vec![0.0; params.n + 1]; // Start from rest
+/// Response body of /ndjson, integrating one sample per poll.
+struct NdjsonFrames {
+    states: States,
+    geometry: NPendulumSolver, // Copy of the integrated solver, for the link lengths at each t
+    deadline: Instant,
+    max_millis: u64,
+    done: bool, // Set after an error line, the stream ends on the next poll
+}
 
-    // 4. Initialize Solver
-    let solver = NPendulumSolver::new(params.n, full_masses, full_lengths.clone());
+impl MessageBody for NdjsonFrames {
+    type Error = Infallible;
 
-    // 5. Run Simulation
-    // returns (time_vector, state_vectors)
-    let (_t, sol) = solver.solve(
-        full_angles,
-        initial_ang_vels,
-        params.t_max,
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<std::result::Result<Bytes, Infallible>>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+        let Some((t, state)) = this.states.next() else {
+            // A singular mass matrix ends the iteration early; report it like the other failures
+            return Poll::Ready(this.states.error().map(|e| {
+                this.done = true;
+                let line = serde_json::json!({ "error": e.to_string() }).to_string() + "\n";
+                Ok(Bytes::from(line))
+            }));
+        };
+        let line = if !state.iter().all(|v| v.is_finite()) {
+            this.done = true;
+            serde_json::json!({ "error": Diverged { t }.to_string() }).to_string()
+        } else if Instant::now() > this.deadline {
+            this.done = true;
+            serde_json::json!({ "error": ParamError::TimeBudget { max_millis: this.max_millis, t }.to_string() }).to_string()
+        } else {
+            let positions = state_to_cartesian(&this.geometry.lengths_at(t), state.as_slice(), this.geometry.n);
+            serde_json::to_string(&NdjsonFrame { t, positions: &positions }).unwrap_or_default()
+        };
+        let mut bytes = line.into_bytes();
+        bytes.push(b'\n');
+        Poll::Ready(Some(Ok(Bytes::from(bytes))))
+    }
+}
+
+/// Helper: Runs one simulation and builds its response body, shared by /simulate and /batch.
+fn simulate(params: &SimParams) -> SimResponse {
+    simulate_within(params, budget_deadline(params))
+}
+
+/// `simulate` that stops at `deadline` instead of after the request's own `max_millis`.
+fn simulate_within(params: &SimParams, deadline: Instant) -> SimResponse {
+    log::info!(
+        "simulate: start n={} n_points={} t_max={} integrator={:?} substeps={}",
+        params.n, params.n_points, params.t_max, params.integrator, params.substeps
+    );
+    let started = Instant::now();
+    let run = match prepare_sim(params).and_then(|prepared| run_prepared_within(params, prepared, deadline, |_, _| false)) {
+        Ok(run) => run,
+        Err(e) => {
+            match e {
+                ParamError::Diverged(_) | ParamError::Singular(_) | ParamError::TimeBudget { .. } => {
+                    log::error!("simulate: {}", e)
+                }
+                _ => log::warn!("simulate: rejected: {}", e),
+            }
+            return sim_failure(e.to_string());
+        }
+    };
+    let solve_time = started.elapsed();
+    if run.out_of_time {
+        log::warn!("simulate: time budget of {} ms exceeded, returning the truncated trajectory", params.max_millis);
+    }
+    if let Some(t) = run.diverged_at {
+        log::warn!("simulate: diverged at t = {}, returning the truncated trajectory", t);
+    }
+
+    let max_rod_tensions = match run.solver.max_rod_tensions(&run.t, &run.sol) {
+        Ok(tensions) => tensions,
+        Err(e) => return sim_failure(e.to_string()),
+    };
+    let diagnostics = Diagnostics {
+        max_rod_length_error: run.solver.max_rod_length_error(&run.t, &run.positions),
+        arc_lengths: run.solver.arc_lengths(&run.t, &run.sol),
+        max_rod_tensions,
+        mean_positions: run.solver.mean_positions(&run.t, &run.sol),
+        mean_angles: run.solver.mean_angles(&run.t, &run.sol).into_iter().map(AngleStats::from).collect(),
+        min_bob_clearance: run.solver.min_bob_clearance(&run.t, &run.sol),
+    };
+
+    // 6. Render the trajectory plot (a rendering failure still returns the animation data)
+    let (plot_image, mut message) = match plot::trajectory_png_base64(&run.plot(), run.supersample) {
+        Ok(img) => (Some(img), None),
+        Err(e) => (None, Some(format!("Plot rendering failed: {}", e))),
+    };
+    let energy_plot_image = if params.energy_plot {
+        let energy: Vec<f64> = run.sol.iter().map(|state| run.solver.energy(state)).collect();
+        match plot::energy_png_base64(&run.t, &energy) {
+            Ok(img) => Some(img),
+            Err(e) => {
+                message.get_or_insert(format!("Energy plot rendering failed: {}", e));
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let mut warnings = Vec::new();
+    if let Some(t) = run.diverged_at {
+        warnings.push(format!("Simulation diverged at t = {}, the trajectory is truncated there", t));
+    }
+    if run.out_of_time {
+        warnings.push(format!(
+            "Time budget of {} ms exceeded at t = {}, the trajectory is truncated there",
+            params.max_millis,
+            run.t.last().copied().unwrap_or(0.0)
+        ));
+    }
+    if let Some(y0) = run.sol.first() {
+        let suggested = run.solver.suggest_n_points(y0, params.t_max);
+        if params.n_points < suggested / 2 {
+            warnings.push(format!(
+                "n_points = {} is well below the {} suggested for this start ({} steps per fastest period), results may be inaccurate",
+                params.n_points,
+                suggested,
+                STEPS_PER_PERIOD
+            ));
+        }
+    }
+    if !warnings.is_empty() {
+        message = Some(warnings.into_iter().chain(message).collect::<Vec<_>>().join("; "));
+    }
+
+    log::info!(
+        "simulate: n={} n_points={} t_max={} done, solve {:.1?}, total {:.1?}, {} samples, completed={}",
+        params.n,
         params.n_points,
+        params.t_max,
+        solve_time,
+        started.elapsed(),
+        run.t.len(),
+        run.completed
     );
 
-    // 6. Post-Process Results
-    // Calculate display limit (Total length + padding)
-    let limit: f64 = lengths.iter().sum::<f64>() + 0.5;
-    
-    // Convert angles to Cartesian coordinates for the frontend
-    let positions = compute_positions(&sol, params.n, &full_lengths);
+    let n = run.solver.n;
+    let (positions, ang_vels) = match params.positions_format {
+        PositionsFormat::Cartesian => (run.positions, None),
+        PositionsFormat::Angles => (
+            run.sol.iter().map(|state| state.rows(0, n).iter().copied().collect()).collect(),
+            params
+                .include_ang_vels
+                .then(|| run.sol.iter().map(|state| state.rows(n, n).iter().copied().collect()).collect()),
+        ),
+    };
+    let (positions, ang_vels) = match params.layout {
+        Layout::Row => (positions, ang_vels),
+        Layout::Column => (export::transpose(&positions), ang_vels.as_deref().map(export::transpose)),
+    };
+
+    #[cfg(feature = "hash")]
+    let trajectory_hash = Some(export::trajectory_hash(&run.sol));
+    #[cfg(not(feature = "hash"))]
+    let trajectory_hash = None;
 
-    // 7. Return JSON
-    Ok(HttpResponse::Ok().json(SimResponse {
+    // 7. Build the response
+    SimResponse {
         success: true,
+        completed: run.completed,
+        t_reached: run.t.last().copied().unwrap_or(0.0),
+        diverged_at: run.diverged_at,
         animation_data: AnimationData {
             positions,
+            ang_vels,
             n: params.n,
-            limit,
+            limit: run.limit,
         },
+        plot_image,
+        energy_plot_image,
+        diagnostics: Some(diagnostics),
+        trajectory_hash,
+        seed: run.seed,
+        message,
+    }
+}
+
+/// Applies `f` to every item, in parallel with the `rayon` feature, and returns the results in input
+/// order whatever the thread scheduling. Each result is written to its item's index in a pre-sized
+/// vector, so the order does not rest on how the parallel iterator collects.
+fn map_in_order<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync + Send,
+{
+    #[cfg(feature = "rayon")]
+    let indexed: Vec<(usize, R)> = {
+        use rayon::prelude::*;
+        items.par_iter().enumerate().map(|(i, item)| (i, f(item))).collect()
+    };
+    #[cfg(not(feature = "rayon"))]
+    let indexed: Vec<(usize, R)> = items.iter().enumerate().map(|(i, item)| (i, f(item))).collect();
+
+    let mut slots: Vec<Option<R>> = std::iter::repeat_with(|| None).take(items.len()).collect();
+    for (i, result) in indexed {
+        slots[i] = Some(result);
+    }
+    slots.into_iter().map(|slot| slot.expect("every index is produced once")).collect()
+}
+
+/// Cost caps for /batch: simulations per request, samples per simulation and samples in total.
+const MAX_BATCH_SIZE: usize = 64;
+const MAX_BATCH_POINTS_PER_RUN: usize = 1_000_000;
+const MAX_BATCH_POINTS: usize = 20_000_000;
+
+#[derive(Serialize)]
+struct BatchResponse {
+    success: bool,
+    results: Vec<SimResponse>, // One per submitted configuration, in order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Batch Handler: Runs independent simulations from one request, in parallel with the `rayon` feature.
+/// A configuration over the per-run cap fails on its own; exceeding the total cap fails the request.
+/// Each run keeps its own max_millis, and the whole batch shares the longest of them.
+pub async fn batch_handler(params: web::Json<Vec<SimParams>>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(BatchResponse {
+            success: false,
+            results: Vec::new(),
+            message: Some(message),
+        }))
+    };
+
+    if params.is_empty() || params.len() > MAX_BATCH_SIZE {
+        return fail(format!("Batch must hold between 1 and {} simulations, got {}", MAX_BATCH_SIZE, params.len()));
+    }
+    let total_points: usize = params.iter().map(|p| p.n_points.min(MAX_BATCH_POINTS_PER_RUN)).sum();
+    if total_points > MAX_BATCH_POINTS {
+        return fail(format!("Batch needs {} samples in total, the limit is {}", total_points, MAX_BATCH_POINTS));
+    }
+
+    let params = params.into_inner();
+    let batch_millis = params.iter().map(|p| p.max_millis).max().unwrap_or(0).min(MAX_MILLIS);
+    let batch_deadline = Instant::now() + Duration::from_millis(batch_millis);
+    let run_one = move |p: &SimParams| {
+        if p.n_points > MAX_BATCH_POINTS_PER_RUN {
+            sim_failure(format!("n_points must be at most {} in a batch, got {}", MAX_BATCH_POINTS_PER_RUN, p.n_points))
+        } else {
+            simulate_within(p, batch_deadline.min(budget_deadline(p)))
+        }
+    };
+
+    // The solves are CPU-bound, keep them off the async workers
+    let results = web::block(move || map_in_order(&params, run_one)).await?;
+
+    Ok(HttpResponse::Ok().json(BatchResponse {
+        success: true,
+        results,
+        message: None,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct PoseParams {
+    #[serde(flatten)]
+    sim: SimParams,
+    t: f64, // Query time (s)
+}
+
+#[derive(Serialize)]
+struct PoseResponse {
+    success: bool,
+    t: f64,
+    positions: Vec<(f64, f64)>, // [(x1, y1), (x2, y2), ...]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Pose Handler: Returns the bob positions at a single time, for scrubbing without the full trajectory.
+pub async fn pose_handler(params: web::Json<PoseParams>) -> Result<HttpResponse> {
+    let prepared = match prepare_sim(&params.sim) {
+        Ok(p) => p,
+        Err(e) => {
+            return Ok(HttpResponse::Ok().json(PoseResponse {
+                success: false,
+                t: params.t,
+                positions: Vec::new(),
+                message: Some(e.to_string()),
+            }))
+        }
+    };
+
+    // Use the same step size as the full trajectory so poses line up with animation frames
+    let max_dt = params.sim.t_max / (params.sim.n_points.max(2) - 1) as f64;
+    let positions = match prepared.solver.pose_at(
+        &prepared.initial_angles,
+        &prepared.initial_ang_vels,
+        params.t,
+        max_dt,
+    ) {
+        Ok(positions) => positions,
+        Err(e) => {
+            return Ok(HttpResponse::Ok().json(PoseResponse {
+                success: false,
+                t: params.t,
+                positions: Vec::new(),
+                message: Some(e.to_string()),
+            }))
+        }
+    };
+
+    Ok(HttpResponse::Ok().json(PoseResponse {
+        success: true,
+        t: params.t,
+        positions,
+        message: None,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct EnsembleParams {
+    #[serde(flatten)]
+    sim: SimParams,
+    members: usize,  // Number of perturbed copies
+    spread_deg: f64, // Max uniform jitter added to each initial angle (degrees)
+    // The shared `seed` key fixes both the "random" preset and the jitter
+}
+
+#[derive(Serialize)]
+struct EnsembleMember {
+    initial_angles: Vec<f64>,      // Perturbed initial angles (degrees)
+    final_positions: Vec<(f64, f64)>,
+}
+
+#[derive(Serialize)]
+struct EnsembleResponse {
+    success: bool,
+    seed: u64,
+    members: Vec<EnsembleMember>,
+    final_angles: Vec<AngleStats>, // Spread of each link's final angle across the members
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Ensemble Handler: Jitters the initial angles and reports where each copy ends up at `t_max`.
+pub async fn ensemble_handler(params: web::Json<EnsembleParams>) -> Result<HttpResponse> {
+    let mut params = params.into_inner();
+    let seed = params.sim.seed.unwrap_or_else(rand::random);
+    params.sim.seed = Some(seed);
+    // A separate stream for the jitter, so it does not replay the preset's draws
+    let (mut rng, _) = seeded_rng(Some(seed.wrapping_add(1)));
+
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(EnsembleResponse {
+            success: false,
+            seed,
+            members: Vec::new(),
+            final_angles: Vec::new(),
+            message: Some(message),
+        }))
+    };
+
+    let prepared = match prepare_sim(&params.sim) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+    if params.members == 0 || params.members > 64 {
+        return fail(format!("members must be between 1 and 64, got {}", params.members));
+    }
+
+    let spread = params.spread_deg.abs().to_radians();
+    let max_dt = params.sim.t_max / (params.sim.n_points.max(2) - 1) as f64;
+
+    // Draw every perturbation up front, in member order, so the seed fixes the ensemble however
+    // the integrations below are scheduled
+    let starts: Vec<Vec<f64>> = (0..params.members)
+        .map(|_| {
+            let mut angles = prepared.initial_angles.clone();
+            for theta in angles.iter_mut().skip(1) {
+                *theta += rng.random_range(-spread..=spread);
+            }
+            angles
+        })
+        .collect();
+    let finals = map_in_order(&starts, |angles| {
+        prepared.solver.pose_at(angles, &prepared.initial_ang_vels, params.sim.t_max, max_dt)
+    });
+
+    let mut members = Vec::with_capacity(params.members);
+    for (angles, final_positions) in starts.iter().zip(finals) {
+        let final_positions = match final_positions {
+            Ok(positions) => positions,
+            Err(e) => return fail(e.to_string()),
+        };
+        members.push(EnsembleMember {
+            initial_angles: angles[1..].iter().map(|a| a.to_degrees()).collect(),
+            final_positions,
+        });
+    }
+
+    // Final link angles from the bob positions, averaged on the circle: members that flipped over
+    // end near ±π and would otherwise average to pointing down
+    let final_angles = (0..params.sim.n)
+        .map(|k| {
+            let angles: Vec<f64> = members
+                .iter()
+                .map(|m| {
+                    let (x, y) = m.final_positions[k];
+                    let (px, py) = if k == 0 { (0.0, 0.0) } else { m.final_positions[k - 1] };
+                    (x - px).atan2(-(y - py))
+                })
+                .collect();
+            AngleStats::from(circular::stats(&angles))
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(EnsembleResponse {
+        success: true,
+        seed,
+        members,
+        final_angles,
         message: None,
     }))
-}
\ No newline at end of file
+}
+/// Scalar that a sweep varies.
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SweepParameter {
+    Mass,    // m_link (kg)
+    Length,  // L_link (m)
+    Angle,   // Initial θ_link (degrees)
+    Gravity, // g (m/s²)
+}
+
+/// Aggregate recorded for each swept value.
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SweepMetric {
+    FinalEnergy, // Total energy at t_max (J)
+    FlipTime,    // First time any link passes over the top (s), null if never
+    MaxSpeed,    // Fastest bob speed over the run (m/s)
+}
+
+#[derive(Deserialize)]
+pub struct SweepParams {
+    #[serde(flatten)]
+    sim: SimParams,
+    parameter: SweepParameter,
+    link: Option<usize>, // 1-based link index, required for mass/length/angle
+    start: f64,
+    end: f64,
+    steps: usize,        // Number of sampled values, including both ends
+    metric: SweepMetric,
+}
+
+#[derive(Serialize)]
+struct SweepResponse {
+    success: bool,
+    values: Vec<f64>,
+    metric: Vec<Option<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plot_image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Sweep Handler: Re-runs the simulation across a range of one parameter and reports a metric per value.
+pub async fn sweep_handler(params: web::Json<SweepParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(SweepResponse {
+            success: false,
+            values: Vec::new(),
+            metric: Vec::new(),
+            plot_image: None,
+            message: Some(message),
+        }))
+    };
+
+    let prepared = match prepare_sim(&params.sim) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+    if params.steps < 2 || params.steps > 100 {
+        return fail(format!("steps must be between 2 and 100, got {}", params.steps));
+    }
+    let link = match (params.parameter, params.link) {
+        (SweepParameter::Gravity, _) => 0,
+        (_, Some(k)) if (1..=params.sim.n).contains(&k) => k,
+        _ => return fail(format!("link must be between 1 and {}", params.sim.n)),
+    };
+
+    let values: Vec<f64> = (0..params.steps)
+        .map(|i| params.start + (params.end - params.start) * i as f64 / (params.steps - 1) as f64)
+        .collect();
+
+    let mut metric = Vec::with_capacity(values.len());
+    // One max_millis budget for the whole sweep
+    let deadline = budget_deadline(&params.sim);
+    for &value in &values {
+        let mut solver = prepared.solver.clone();
+        let mut angles = prepared.initial_angles.clone();
+        match params.parameter {
+            SweepParameter::Mass if value >= 0.0 => solver.masses[link] = value,
+            SweepParameter::Length if value > 0.0 => solver.lengths[link] = value,
+            SweepParameter::Angle => angles[link] = value.to_radians(),
+            SweepParameter::Gravity => solver.g = value,
+            _ => return fail(format!("Swept value {} is not physical for this parameter", value)),
+        }
+        if let Err(e) = check_mass_pattern(&solver.masses) {
+            return fail(format!("At swept value {}: {}", value, e));
+        }
+
+        let Integration { t, sol, .. } =
+            match integrate(&params.sim, &solver, angles, prepared.initial_ang_vels.clone(), deadline, |_, _| false) {
+                Ok(run) => run,
+                Err(e) => return fail(format!("At swept value {}: {}", value, e)),
+            };
+        metric.push(match params.metric {
+            SweepMetric::FinalEnergy => sol.last().map(|state| solver.energy(state)),
+            SweepMetric::FlipTime => solver.flip_time(&t, &sol),
+            SweepMetric::MaxSpeed => Some(solver.max_bob_speed(&t, &sol).0),
+        });
+    }
+
+    let (x_desc, y_desc) = (
+        match params.parameter {
+            SweepParameter::Mass => format!("m{} (kg)", link),
+            SweepParameter::Length => format!("L{} (m)", link),
+            SweepParameter::Angle => format!("θ{} (deg)", link),
+            SweepParameter::Gravity => "g (m/s²)".to_string(),
+        },
+        match params.metric {
+            SweepMetric::FinalEnergy => "Final energy (J)",
+            SweepMetric::FlipTime => "Flip time (s)",
+            SweepMetric::MaxSpeed => "Max bob speed (m/s)",
+        },
+    );
+    let points: Vec<(f64, f64)> = values
+        .iter()
+        .zip(&metric)
+        .filter_map(|(&x, y)| y.map(|y| (x, y)))
+        .collect();
+    let (plot_image, message) = match plot::xy_plot_png_base64(
+        "Parameter sweep",
+        &x_desc,
+        y_desc,
+        &[(y_desc.to_string(), points)],
+    ) {
+        Ok(img) => (Some(img), None),
+        Err(e) => (None, Some(format!("Plot rendering failed: {}", e))),
+    };
+
+    Ok(HttpResponse::Ok().json(SweepResponse {
+        success: true,
+        values,
+        metric,
+        plot_image,
+        message,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct BifurcationParams {
+    #[serde(flatten)]
+    sim: SimParams, // Base configuration incl. its per-joint damping (needed for attractors), t_max and n_points are not used
+    #[serde(default = "default_link")]
+    link: usize,              // Driven (and observed) joint, 1-based
+    drive_frequency: f64,     // Ω (rad/s)
+    amplitude_start: f64,     // Drive torque amplitude range (N·m)
+    amplitude_end: f64,
+    amplitude_steps: usize,   // Resolution along the amplitude axis
+    transient: f64,           // Time discarded before sampling (s)
+    samples: usize,           // Stroboscopic samples kept per amplitude
+    #[serde(default = "default_steps_per_period")]
+    steps_per_period: usize,  // RK4 steps per drive period
+}
+
+fn default_link() -> usize {
+    1
+}
+
+fn default_steps_per_period() -> usize {
+    100
+}
+
+#[derive(Serialize)]
+struct BifurcationResponse {
+    success: bool,
+    points: Vec<(f64, f64)>, // (amplitude, ω_link sampled once per drive period)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plot_image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Bifurcation Handler: Stroboscopically samples ω of a driven chain across drive amplitudes.
+pub async fn bifurcation_handler(params: web::Json<BifurcationParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(BifurcationResponse {
+            success: false,
+            points: Vec::new(),
+            plot_image: None,
+            message: Some(message),
+        }))
+    };
+
+    let prepared = match prepare_sim(&params.sim) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+    if params.amplitude_steps < 2 || params.samples == 0 || params.steps_per_period < 4 {
+        return fail("Need amplitude_steps >= 2, samples >= 1 and steps_per_period >= 4".to_string());
+    }
+    if params.transient < 0.0 || !params.transient.is_finite() {
+        return fail("transient must be a non-negative time".to_string());
+    }
+
+    if !(params.drive_frequency.is_finite() && params.drive_frequency != 0.0) {
+        return fail(format!("drive_frequency must be finite and non-zero, got {}", params.drive_frequency));
+    }
+
+    let period = 2.0 * std::f64::consts::PI / params.drive_frequency.abs();
+    // Client-sized counts: any overflow is far past the step limit anyway
+    let total_steps = ((params.transient / period).ceil() as usize)
+        .checked_add(params.samples)
+        .and_then(|periods_per_run| periods_per_run.checked_mul(params.amplitude_steps))
+        .and_then(|periods| periods.checked_mul(params.steps_per_period));
+    match total_steps {
+        Some(steps) if steps <= 20_000_000 => {}
+        Some(steps) => return fail(format!("Request needs {} integration steps, the limit is 20000000", steps)),
+        None => return fail("Request needs more than 20000000 integration steps".to_string()),
+    }
+
+    let mut points = Vec::with_capacity(params.amplitude_steps * params.samples);
+    // One max_millis budget for the whole sweep
+    let deadline = budget_deadline(&params.sim);
+    for i in 0..params.amplitude_steps {
+        let amplitude = params.amplitude_start
+            + (params.amplitude_end - params.amplitude_start) * i as f64 / (params.amplitude_steps - 1) as f64;
+
+        // Everything else the request configured (integrator, tilt, controller, ...) carries over
+        let forcing = Forcing {
+            link: params.link,
+            amplitude,
+            frequency: params.drive_frequency,
+        };
+        if let Err(message) = forcing.check(params.sim.n) {
+            return fail(message);
+        }
+        let mut solver = prepared.solver.clone();
+        solver.forcing = Some(forcing);
+
+        let (mut steps, mut out_of_time) = (0usize, None);
+        let samples = match solver.stroboscopic_samples(
+            &prepared.initial_angles,
+            &prepared.initial_ang_vels,
+            period,
+            params.transient,
+            params.samples,
+            params.steps_per_period,
+            |t, _| {
+                steps += 1;
+                if steps.is_multiple_of(BUDGET_CHECK_INTERVAL) && Instant::now() >= deadline {
+                    out_of_time = Some(t);
+                }
+                out_of_time.is_some()
+            },
+        ) {
+            Ok(samples) => samples,
+            Err(e) => return fail(format!("At drive amplitude {}: {}", amplitude, e)),
+        };
+        let omega_idx = params.sim.n + params.link - 1;
+        points.extend(samples.iter().map(|state| (amplitude, state[omega_idx])));
+
+        if let Some(t) = out_of_time {
+            if params.sim.on_divergence == OnDivergence::Error {
+                let e = ParamError::TimeBudget {
+                    max_millis: params.sim.max_millis,
+                    t,
+                };
+                return fail(format!("At drive amplitude {}: {}", amplitude, e));
+            }
+            break; // "truncate": keep the amplitudes sampled so far
+        }
+    }
+
+    let y_desc = format!("ω{} (rad/s)", params.link);
+    let (plot_image, message) = match plot::scatter_plot_png_base64(
+        "Bifurcation diagram",
+        "Drive amplitude (N·m)",
+        &y_desc,
+        &[(y_desc.clone(), points.clone())],
+    ) {
+        Ok(img) => (Some(img), None),
+        Err(e) => (None, Some(format!("Plot rendering failed: {}", e))),
+    };
+
+    Ok(HttpResponse::Ok().json(BifurcationResponse {
+        success: true,
+        points,
+        plot_image,
+        message,
+    }))
+}
+
+#[derive(Serialize)]
+struct PeriodsResponse {
+    success: bool,
+    frequencies: Vec<f64>, // Normal-mode angular frequencies (rad/s), slowest first
+    periods: Vec<f64>,     // 2π/ω (s), matching `frequencies`; null where ω = 0
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Periods Handler: Analytic small-oscillation periods of the linearized chain,
+/// to compare against what the simulation shows for small initial angles.
+pub async fn periods_handler(params: web::Json<SimParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(PeriodsResponse {
+            success: false,
+            frequencies: Vec::new(),
+            periods: Vec::new(),
+            message: Some(message),
+        }))
+    };
+
+    let prepared = match prepare_sim(&params) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+    let solver = &prepared.solver;
+    if solver.masses[1..].iter().any(|&m| m.is_nan() || m <= 0.0) {
+        return fail("Normal modes need every mass to be positive".to_string());
+    }
+    if solver.lengths[1..].iter().any(|&l| l.is_nan() || l <= 0.0) {
+        return fail("Normal modes need every length to be positive".to_string());
+    }
+    if solver.controller.is_some() {
+        return fail("Normal modes are those of the free chain, remove the controller".to_string());
+    }
+
+    let (frequencies, periods) = match (solver.normal_mode_frequencies(), solver.small_oscillation_periods()) {
+        (Ok(frequencies), Ok(periods)) => (frequencies, periods),
+        (Err(e), _) | (_, Err(e)) => return fail(e.to_string()),
+    };
+
+    Ok(HttpResponse::Ok().json(PeriodsResponse {
+        success: true,
+        frequencies,
+        periods,
+        message: None,
+    }))
+}
+
+#[derive(Serialize)]
+struct MaxSpeedResponse {
+    success: bool,
+    max_speed: f64,    // Fastest bob speed over the run (m/s)
+    t_at_max: f64,     // When it happens (s)
+    link_index: usize, // Which bob, 1-based (0 if every bob stays at rest)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Max Speed Handler: Runs the simulation and reports only its fastest bob, without the trajectory.
+pub async fn max_speed_handler(params: web::Json<SimParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(MaxSpeedResponse {
+            success: false,
+            max_speed: 0.0,
+            t_at_max: 0.0,
+            link_index: 0,
+            message: Some(message),
+        }))
+    };
+
+    let run = match run_simulation(&params) {
+        Ok(run) => run,
+        Err(e) => return fail(e.to_string()),
+    };
+    let (max_speed, t_at_max, link_index) = run.solver.max_bob_speed(&run.t, &run.sol);
+
+    Ok(HttpResponse::Ok().json(MaxSpeedResponse {
+        success: true,
+        max_speed,
+        t_at_max,
+        link_index,
+        message: None,
+    }))
+}
+
+/// Query flags of `/summary`. The expensive metrics are on by default; pass `false` to skip them.
+#[derive(Deserialize)]
+pub struct SummaryFlags {
+    #[serde(default = "default_true")]
+    energy: bool,    // Energy drift, one energy evaluation per sample
+    #[serde(default = "default_true")]
+    clearance: bool, // Min bob clearance, O(steps · n²)
+    #[serde(default = "default_settling_band")]
+    band: f64,       // Rest band for the settling time (degrees)
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Serialize, Default)]
+struct SummaryResponse {
+    success: bool,
+    max_speed: f64,            // Fastest bob speed over the run (m/s)
+    max_speed_link: usize,     // Which bob, 1-based (0 if every bob stays at rest)
+    flip_time: Option<f64>,    // First time any link passes over the top (s), null if never
+    arc_lengths: Vec<f64>,     // Path length traced by each bob (m)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    energy_drift: Option<f64>, // max |E(t) - E0| (J), omitted with energy=false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    relative_energy_drift: Option<Option<f64>>, // energy_drift / |E0|, null when E0 = 0
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_bob_clearance: Option<Option<BobClearance>>, // Omitted with clearance=false, null below n = 3
+    #[serde(skip_serializing_if = "Option::is_none")]
+    settling_time: Option<Option<f64>>, // When the last link settled, null if one never did; omitted when undamped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Runs the simulation once and reduces it to the scalars of `SummaryResponse`.
+fn summarize(params: &SimParams, flags: &SummaryFlags) -> SummaryResponse {
+    let fail = |message: String| SummaryResponse {
+        message: Some(message),
+        ..Default::default()
+    };
+
+    let prepared = match prepare_sim(params) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+    // Settling time is only reported when the request damps at least one joint
+    let settles = prepared.solver.damping.iter().any(|&b| b > 0.0);
+    if settles {
+        if !(flags.band > 0.0 && flags.band < 180.0) {
+            return fail("band must be between 0 and 180 degrees".to_string());
+        }
+        if params.gravity_rotation_rate != 0.0 {
+            return fail("Settling is measured against a fixed rest position, gravity must not rotate".to_string());
+        }
+    }
+
+    let SimRun { solver, t, sol, .. } = match run_prepared(params, prepared, |_, _| false) {
+        Ok(run) => run,
+        Err(e) => return fail(e.to_string()),
+    };
+    let (max_speed, _, max_speed_link) = solver.max_bob_speed(&t, &sol);
+    let energy_drift = flags.energy.then(|| solver.max_energy_drift(&t, &sol));
+    let relative_energy_drift = energy_drift.map(|drift| {
+        let e0 = sol.first().map_or(0.0, |state| solver.energy_at(t[0], state)).abs();
+        (e0 > 0.0).then(|| drift / e0)
+    });
+    let settling_time = settles.then(|| {
+        solver
+            .settling_times(&t, &sol, flags.band.to_radians())
+            .iter()
+            .try_fold(f64::NEG_INFINITY, |latest, time| time.map(|time| latest.max(time)))
+    });
+
+    SummaryResponse {
+        success: true,
+        max_speed,
+        max_speed_link,
+        flip_time: solver.flip_time(&t, &sol),
+        arc_lengths: solver.arc_lengths(&t, &sol),
+        energy_drift,
+        relative_energy_drift,
+        min_bob_clearance: flags.clearance.then(|| solver.min_bob_clearance(&t, &sol)),
+        settling_time,
+        message: None,
+    }
+}
+
+/// Summary Handler: One-shot scalars of a run for dashboards, with the simulation parameters as JSON.
+pub async fn summary_handler(params: web::Json<SimParams>, flags: web::Query<SummaryFlags>) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(summarize(&params, &flags)))
+}
+
+/// Summary Handler for GET: the simulation parameters come from the query string next to the flags,
+/// e.g. `/summary?n=2&masses=1&lengths=1&initial_angles=90,0&t_max=10&n_points=1001&clearance=false`.
+pub async fn summary_query_handler(
+    params: web::Query<SimParams>,
+    flags: web::Query<SummaryFlags>,
+) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(summarize(&params, &flags)))
+}
+
+#[derive(Serialize)]
+struct PeakHeightResponse {
+    success: bool,
+    t_at_peak: f64,             // When the potential energy peaks (s)
+    potential_energy: f64,      // Its value there (J), relative to the pivot
+    angles: Vec<f64>,           // Pose at the peak (degrees)
+    positions: Vec<(f64, f64)>, // Bob positions at the peak [(x1, y1), ...] (m)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Peak Height Handler: Finds when the chain stores the most potential energy, and its pose then.
+/// In a conservative run this is also the kinetic-energy minimum, the turning point of the energy sloshing.
+pub async fn peak_height_handler(params: web::Json<SimParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(PeakHeightResponse {
+            success: false,
+            t_at_peak: 0.0,
+            potential_energy: 0.0,
+            angles: Vec::new(),
+            positions: Vec::new(),
+            message: Some(message),
+        }))
+    };
+
+    let run = match run_simulation(&params) {
+        Ok(run) => run,
+        Err(e) => return fail(e.to_string()),
+    };
+    let Some((i, potential_energy)) = run.solver.peak_potential_energy(&run.sol) else {
+        return fail("The simulation produced no samples".to_string());
+    };
+
+    let n = run.solver.n;
+    Ok(HttpResponse::Ok().json(PeakHeightResponse {
+        success: true,
+        t_at_peak: run.t[i],
+        potential_energy,
+        angles: run.sol[i].rows(0, n).iter().map(|a| a.to_degrees()).collect(),
+        positions: run.solver.bob_positions(run.t[i], &run.sol[i]),
+        message: None,
+    }))
+}
+
+#[derive(Serialize)]
+struct Equilibrium {
+    angles: Vec<f64>,    // Equilibrium angles (degrees), wrapped into (-180, 180]
+    stiffness: Vec<f64>, // Eigenvalues of ∂²V/∂θ² there (N·m/rad), ascending
+    stable: bool,        // All stiffness eigenvalues positive
+}
+
+#[derive(Serialize)]
+struct EquilibriumResponse {
+    success: bool,
+    equilibria: Vec<Equilibrium>, // Hanging configuration first, then the one reached from the initial angles
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Equilibrium Handler: Confirms the hanging equilibrium and runs Newton on ∂V/∂θ = 0
+/// from the supplied initial angles to find the nearest other one.
+pub async fn equilibrium_handler(params: web::Json<SimParams>) -> Result<HttpResponse> {
+    let prepared = match prepare_sim(&params) {
+        Ok(p) => p,
+        Err(e) => {
+            return Ok(HttpResponse::Ok().json(EquilibriumResponse {
+                success: false,
+                equilibria: Vec::new(),
+                message: Some(e.to_string()),
+            }))
+        }
+    };
+    let solver = &prepared.solver;
+
+    let mut equilibria: Vec<Equilibrium> = Vec::new();
+    let mut message = None;
+    for guess in [vec![0.0; params.n + 1], prepared.initial_angles.clone()] {
+        let Some(angles) = solver.find_equilibrium(&guess, 1e-10, 50) else {
+            message = Some("Newton iteration did not converge from the initial angles".to_string());
+            continue;
+        };
+        let degrees: Vec<f64> = angles[1..].iter().map(|a| a.to_degrees()).collect();
+        if equilibria
+            .iter()
+            .any(|e| e.angles.iter().zip(&degrees).all(|(a, b)| (a - b).abs() < 1e-6))
+        {
+            continue;
+        }
+        let stiffness = solver.equilibrium_stiffness(&angles);
+        equilibria.push(Equilibrium {
+            stable: stiffness.iter().all(|&k| k > 0.0),
+            angles: degrees,
+            stiffness,
+        });
+    }
+
+    Ok(HttpResponse::Ok().json(EquilibriumResponse {
+        success: true,
+        equilibria,
+        message,
+    }))
+}
+
+#[derive(Serialize)]
+struct StateSpaceResponse {
+    success: bool,
+    equilibrium: Vec<f64>,      // Angles linearized about (degrees), Newton-polished from initial_angles
+    a: Vec<Vec<f64>>,           // 2n × 2n, x = [δθ1...δθn, δω1...δωn]
+    b: Vec<Vec<f64>>,           // 2n × n, u = torques on θ1...θn (N·m)
+    eigenvalues: Vec<(f64, f64)>, // Open-loop eigenvalues of A as (re, im) (1/s), by descending real part
+    unstable: bool,             // Some eigenvalue has a positive real part
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// State-Space Handler: Linearized ẋ = A x + B u about the equilibrium nearest `initial_angles`,
+/// the starting point for LQR or pole placement, e.g. about "180,180" for the inverted double pendulum.
+pub async fn state_space_handler(params: web::Json<SimParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(StateSpaceResponse {
+            success: false,
+            equilibrium: Vec::new(),
+            a: Vec::new(),
+            b: Vec::new(),
+            eigenvalues: Vec::new(),
+            unstable: false,
+            message: Some(message),
+        }))
+    };
+
+    let prepared = match prepare_sim(&params) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+    let solver = &prepared.solver;
+    let Some(angles) = solver.find_equilibrium(&prepared.initial_angles, 1e-10, 50) else {
+        return fail("Newton iteration did not converge to an equilibrium from the initial angles".to_string());
+    };
+
+    let (a, b) = solver.linearized_state_space(&angles);
+    let a_matrix = DMatrix::from_fn(a.len(), a.len(), |i, j| a[i][j]);
+    let mut eigenvalues: Vec<(f64, f64)> = a_matrix.complex_eigenvalues().iter().map(|z| (z.re, z.im)).collect();
+    eigenvalues.sort_by(|x, y| y.0.total_cmp(&x.0).then(y.1.total_cmp(&x.1)));
+    // Marginal modes come out at ±1e-8 or so; only count growth well above rounding
+    let scale = eigenvalues.iter().map(|z| z.0.hypot(z.1)).fold(1.0, f64::max);
+
+    Ok(HttpResponse::Ok().json(StateSpaceResponse {
+        success: true,
+        equilibrium: angles[1..].iter().map(|a| a.to_degrees()).collect(),
+        unstable: eigenvalues.iter().any(|z| z.0 > 1e-6 * scale),
+        a,
+        b,
+        eigenvalues,
+        message: None,
+    }))
+}
+
+#[derive(Serialize)]
+struct AccelerationResponse {
+    success: bool,
+    t: Vec<f64>,
+    accelerations: Vec<Vec<f64>>, // [α1, ..., αn] (rad/s²) per time step
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Accelerations Handler: Runs the simulation and returns the angular acceleration time series.
+pub async fn accelerations_handler(params: web::Json<SimParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(AccelerationResponse {
+            success: false,
+            t: Vec::new(),
+            accelerations: Vec::new(),
+            message: Some(message),
+        }))
+    };
+
+    let run = match run_simulation(&params) {
+        Ok(run) => run,
+        Err(e) => return fail(e.to_string()),
+    };
+
+    let accelerations = match run.solver.acceleration_series(&run.t, &run.sol) {
+        Ok(accelerations) => accelerations,
+        Err(e) => return fail(e.to_string()),
+    };
+
+    Ok(HttpResponse::Ok().json(AccelerationResponse {
+        success: true,
+        t: run.t,
+        accelerations,
+        message: None,
+    }))
+}
+
+const MAX_FAN_CHAINS: usize = 16;
+const MAX_GIF_FRAMES: usize = 300; // Per animation, for /fan and /energy_video
+
+#[derive(Deserialize)]
+pub struct FanParams {
+    #[serde(flatten)]
+    sim: SimParams,  // Base configuration shared by every chain
+    #[serde(default = "default_link")]
+    link: usize,     // Link whose release angle is fanned out, 1-based
+    angle_start: f64, // Release angle range for that link (degrees)
+    angle_end: f64,
+    count: usize,    // Number of chains, evenly spaced over the range
+    #[serde(default = "default_frame_stride")]
+    frame_stride: usize, // Keep every k-th sample as a frame
+    #[serde(default = "default_gif_size")]
+    size: u32,       // Frame edge (px)
+}
+
+fn default_frame_stride() -> usize {
+    5
+}
+
+fn default_gif_size() -> u32 {
+    400
+}
+
+#[derive(Serialize)]
+struct FanResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gif: Option<String>,      // Base64 animated GIF
+    release_angles: Vec<f64>, // Release angle of the fanned link per chain (degrees), in color order
+    frames: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Fan Handler: Releases copies of the chain from a fan of angles and animates them diverging.
+pub async fn fan_handler(params: web::Json<FanParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(FanResponse {
+            success: false,
+            gif: None,
+            release_angles: Vec::new(),
+            frames: 0,
+            message: Some(message),
+        }))
+    };
+
+    let prepared = match prepare_sim(&params.sim) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+    if params.count < 2 || params.count > MAX_FAN_CHAINS {
+        return fail(format!("count must be between 2 and {}, got {}", MAX_FAN_CHAINS, params.count));
+    }
+    if !(1..=params.sim.n).contains(&params.link) {
+        return fail(format!("link must be between 1 and {}", params.sim.n));
+    }
+    if params.frame_stride == 0 || !(100..=800).contains(&params.size) {
+        return fail("Need frame_stride >= 1 and size between 100 and 800".to_string());
+    }
+    let n_frames = params.sim.n_points.div_ceil(params.frame_stride);
+    if n_frames > MAX_GIF_FRAMES {
+        return fail(format!(
+            "Animation would have {} frames, the limit is {}; raise frame_stride",
+            n_frames, MAX_GIF_FRAMES
+        ));
+    }
+
+    let release_angles: Vec<f64> = (0..params.count)
+        .map(|i| params.angle_start + (params.angle_end - params.angle_start) * i as f64 / (params.count - 1) as f64)
+        .collect();
+
+    // frames[f][c] = bob positions of chain c in frame f
+    let mut frames: Vec<Vec<Vec<(f64, f64)>>> = vec![Vec::with_capacity(params.count); n_frames];
+    // One max_millis budget for the whole fan
+    let deadline = budget_deadline(&params.sim);
+    for &angle in &release_angles {
+        let mut angles = prepared.initial_angles.clone();
+        angles[params.link] = angle.to_radians();
+        let run = match integrate(&params.sim, &prepared.solver, angles, prepared.initial_ang_vels.clone(), deadline, |_, _| false) {
+            Ok(run) => run,
+            Err(e) => return fail(e.to_string()),
+        };
+        let samples = run.t.iter().zip(&run.sol).step_by(params.frame_stride);
+        for (frame, (&time, state)) in frames.iter_mut().zip(samples) {
+            frame.push(prepared.solver.bob_positions(time, state));
+        }
+    }
+    // A truncated chain ends early; animate only the frames every chain reached
+    frames.retain(|frame| frame.len() == params.count);
+    if frames.is_empty() {
+        return fail("No chain produced a frame before being truncated".to_string());
+    }
+    let n_frames = frames.len();
+
+    // Play back in real time where the GIF format allows it (browsers clamp below 20 ms)
+    let dt = params.sim.t_max.abs() / (params.sim.n_points - 1) as f64;
+    let delay_ms = ((dt * params.frame_stride as f64 * 1000.0).round() as u32).max(20);
+    // All chains share these limits, so the fan stays put from frame to frame
+    let limit = prepared.limit;
+    let caption = format!("Release angle θ{} from {}° to {}°", params.link, params.angle_start, params.angle_end);
+
+    match plot::chains_gif_base64(&frames, limit, params.size, delay_ms, &caption) {
+        Ok(gif) => Ok(HttpResponse::Ok().json(FanResponse {
+            success: true,
+            gif: Some(gif),
+            release_angles,
+            frames: n_frames,
+            message: None,
+        })),
+        Err(e) => fail(format!("GIF rendering failed: {}", e)),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SettlingParams {
+    #[serde(flatten)]
+    sim: SimParams,   // Its per-joint damping must damp at least one joint
+    #[serde(default = "default_settling_band")]
+    band: f64,        // Half-width of the rest band around hanging straight down (degrees)
+}
+
+fn default_settling_band() -> f64 {
+    1.0
+}
+
+#[derive(Serialize)]
+struct SettlingResponse {
+    success: bool,
+    band: f64,                              // Band used (degrees)
+    settling_time: Option<f64>,             // When the last link settled, null if one never did
+    link_settling_times: Vec<Option<f64>>,  // Per link, null where it was still outside the band at t_max
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Settling Handler: Runs the damped chain and reports when each link comes to rest for good.
+pub async fn settling_handler(params: web::Json<SettlingParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(SettlingResponse {
+            success: false,
+            band: params.band,
+            settling_time: None,
+            link_settling_times: Vec::new(),
+            message: Some(message),
+        }))
+    };
+
+    let prepared = match prepare_sim(&params.sim) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+    if prepared.solver.damping.iter().all(|&b| b == 0.0) {
+        return fail("Settling needs damping on at least one joint".to_string());
+    }
+    if !(params.band > 0.0 && params.band < 180.0) {
+        return fail("band must be between 0 and 180 degrees".to_string());
+    }
+    if params.sim.gravity_rotation_rate != 0.0 {
+        return fail("Settling is measured against a fixed rest position, gravity must not rotate".to_string());
+    }
+
+    let run = match run_prepared(&params.sim, prepared, |_, _| false) {
+        Ok(run) => run,
+        Err(e) => return fail(e.to_string()),
+    };
+    let link_settling_times = run.solver.settling_times(&run.t, &run.sol, params.band.to_radians());
+    // The chain has settled once its slowest link has
+    let settling_time = link_settling_times
+        .iter()
+        .try_fold(f64::NEG_INFINITY, |latest, time| time.map(|time| latest.max(time)));
+
+    Ok(HttpResponse::Ok().json(SettlingResponse {
+        success: true,
+        band: params.band,
+        settling_time,
+        link_settling_times,
+        message: None,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct SpectrumParams {
+    #[serde(flatten)]
+    sim: SimParams,
+    #[serde(default = "default_link")]
+    link: usize, // Link whose angle is analyzed, 1-based
+}
+
+#[derive(Serialize)]
+struct SpectrumResponse {
+    success: bool,
+    spectrum: Vec<(f64, f64)>,   // (frequency in Hz, power) from 0 to Nyquist
+    peak_frequency: Option<f64>, // Dominant frequency (Hz)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plot_image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Spectrum Handler: Power spectrum of θ_link(t). Needs the `fft` feature.
+pub async fn spectrum_handler(params: web::Json<SpectrumParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(SpectrumResponse {
+            success: false,
+            spectrum: Vec::new(),
+            peak_frequency: None,
+            plot_image: None,
+            message: Some(message),
+        }))
+    };
+
+    let prepared = match prepare_sim(&params.sim) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+    if !(1..=params.sim.n).contains(&params.link) {
+        return fail(format!("link must be between 1 and {}", params.sim.n));
+    }
+
+    #[cfg(not(feature = "fft"))]
+    {
+        drop(prepared);
+        fail("This server was built without the `fft` feature".to_string())
+    }
+
+    #[cfg(feature = "fft")]
+    {
+        // Every integrator steps at a fixed dt, so the samples are already uniform in time
+        let run = match run_prepared(&params.sim, prepared, |_, _| false) {
+            Ok(run) => run,
+            Err(e) => return fail(e.to_string()),
+        };
+        let theta: Vec<f64> = run.sol.iter().map(|state| state[params.link - 1]).collect();
+        let dt = params.sim.t_max.abs() / (params.sim.n_points - 1) as f64;
+        let spectrum = crate::spectrum::power_spectrum(&theta, dt);
+        let peak_frequency = crate::spectrum::peak_frequency(&spectrum);
+
+        // Log scale so both sharp peaks and a chaotic broadband floor stay visible
+        let floor = spectrum.iter().map(|&(_, p)| p).fold(0.0, f64::max) * 1e-12;
+        let log_power: Vec<(f64, f64)> = spectrum.iter().map(|&(f, p)| (f, p.max(floor).log10())).collect();
+        let (plot_image, message) = match plot::xy_plot_png_base64(
+            &format!("Power spectrum of θ{}", params.link),
+            "Frequency (Hz)",
+            "log₁₀ power",
+            &[(format!("θ{}", params.link), log_power)],
+        ) {
+            Ok(img) => (Some(img), None),
+            Err(e) => (None, Some(format!("Plot rendering failed: {}", e))),
+        };
+
+        Ok(HttpResponse::Ok().json(SpectrumResponse {
+            success: true,
+            spectrum,
+            peak_frequency,
+            plot_image,
+            message,
+        }))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PhaseParams {
+    #[serde(flatten)]
+    sim: SimParams,
+    pendulum_index: usize, // Link whose (θ, ω) orbit is drawn, 0-based
+}
+
+#[derive(Serialize)]
+struct PhaseResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plot_image: Option<String>, // Base64 PNG of ω against θ
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Phase Handler: Draws one link's phase portrait, ω_k against θ_k over the run. A periodic swing
+/// closes into a loop, chaos fills a region; θ is not wrapped, so a link that flips drifts sideways.
+pub async fn phase_plot_handler(params: web::Json<PhaseParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(PhaseResponse {
+            success: false,
+            plot_image: None,
+            message: Some(message),
+        }))
+    };
+
+    let prepared = match prepare_sim(&params.sim) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+    let index = params.pendulum_index;
+    if index >= params.sim.n {
+        return fail(format!("pendulum_index must be below n = {}, got {}", params.sim.n, index));
+    }
+
+    let run = match run_prepared(&params.sim, prepared, |_, _| false) {
+        Ok(run) => run,
+        Err(e) => return fail(e.to_string()),
+    };
+    let orbit: Vec<(f64, f64)> = run.sol.iter().map(|state| (state[index], state[params.sim.n + index])).collect();
+
+    match plot::xy_plot_png_base64(
+        &format!("Phase portrait of link {}", index + 1),
+        &format!("θ{} (rad)", index + 1),
+        &format!("ω{} (rad/s)", index + 1),
+        &[(format!("Pendulum {}", index + 1), orbit)],
+    ) {
+        Ok(img) => Ok(HttpResponse::Ok().json(PhaseResponse {
+            success: true,
+            plot_image: Some(img),
+            message: None,
+        })),
+        Err(e) => fail(format!("Plot rendering failed: {}", e)),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ZeroCrossingParams {
+    #[serde(flatten)]
+    sim: SimParams,
+    #[serde(default = "default_link")]
+    link: usize, // Link whose angle is analyzed, 1-based
+}
+
+#[derive(Serialize)]
+struct ZeroCrossingResponse {
+    success: bool,
+    frequency: Option<f64>, // Dominant frequency (Hz), null with too few crossings
+    period: Option<f64>,    // 1 / frequency (s)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Zero Crossing Handler: Dominant frequency of θ_link(t) from its sign changes, the FFT-free
+/// counterpart of /spectrum for near-periodic motion.
+pub async fn zero_crossing_handler(params: web::Json<ZeroCrossingParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(ZeroCrossingResponse {
+            success: false,
+            frequency: None,
+            period: None,
+            message: Some(message),
+        }))
+    };
+
+    let prepared = match prepare_sim(&params.sim) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+    if !(1..=params.sim.n).contains(&params.link) {
+        return fail(format!("link must be between 1 and {}", params.sim.n));
+    }
+
+    let run = match run_prepared(&params.sim, prepared, |_, _| false) {
+        Ok(run) => run,
+        Err(e) => return fail(e.to_string()),
+    };
+    let frequency = run.solver.zero_crossing_frequency(&run.t, &run.sol, params.link);
+
+    Ok(HttpResponse::Ok().json(ZeroCrossingResponse {
+        success: true,
+        frequency,
+        period: frequency.map(|f| 1.0 / f),
+        message: frequency
+            .is_none()
+            .then(|| format!("θ{} crossed zero fewer than three times, no frequency estimate", params.link)),
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct AngleEventParams {
+    #[serde(flatten)]
+    sim: SimParams,
+    #[serde(default = "default_link")]
+    link: usize,            // Link whose angle is watched, 1-based
+    comparison: Comparison, // "greater" or "less"
+    threshold: f64,         // Absolute, unwrapped angle (degrees), at most MAX_EVENT_THRESHOLD in magnitude
+}
+
+/// Largest |threshold| for /angle_event (degrees): ten full turns of an unwrapped angle.
+const MAX_EVENT_THRESHOLD: f64 = 3600.0;
+
+#[derive(Serialize)]
+struct AngleEventResponse {
+    success: bool,
+    t: Option<f64>,              // Interpolated time the condition first holds (s), null if never within t_max
+    angles: Option<Vec<f64>>,    // [θ1, ..., θn] at t (degrees)
+    ang_vels: Option<Vec<f64>>,  // [ω1, ..., ωn] at t (rad/s)
+    positions: Option<Vec<(f64, f64)>>, // Bob positions at t (m)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Angle Event Handler: Integrates until θ_link first goes above (or below) a threshold and
+/// returns the interpolated time and state there, a generalized flip-time detector.
+pub async fn angle_event_handler(params: web::Json<AngleEventParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(AngleEventResponse {
+            success: false,
+            t: None,
+            angles: None,
+            ang_vels: None,
+            positions: None,
+            message: Some(message),
+        }))
+    };
+
+    let prepared = match prepare_sim(&params.sim) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+    if !(1..=params.sim.n).contains(&params.link) {
+        return fail(format!("link must be between 1 and {}", params.sim.n));
+    }
+    if !(-MAX_EVENT_THRESHOLD..=MAX_EVENT_THRESHOLD).contains(&params.threshold) {
+        return fail(format!(
+            "threshold must be between -{} and {} degrees, got {}",
+            MAX_EVENT_THRESHOLD, MAX_EVENT_THRESHOLD, params.threshold
+        ));
+    }
+
+    let condition = AngleCondition {
+        link: params.link,
+        comparison: params.comparison,
+        threshold: params.threshold.to_radians(),
+    };
+    let n = params.sim.n;
+    let event = prepared.solver.first_angle_event(
+        prepared.initial_angles,
+        prepared.initial_ang_vels,
+        params.sim.t_max,
+        params.sim.n_points,
+        condition,
+    );
+
+    Ok(HttpResponse::Ok().json(match event {
+        Some(event) => AngleEventResponse {
+            success: true,
+            t: Some(event.t),
+            angles: Some(event.state.rows(0, n).iter().map(|a| a.to_degrees()).collect()),
+            ang_vels: Some(event.state.rows(n, n).iter().copied().collect()),
+            positions: Some(prepared.solver.bob_positions(event.t, &event.state)),
+            message: None,
+        },
+        None => AngleEventResponse {
+            success: true,
+            t: None,
+            angles: None,
+            ang_vels: None,
+            positions: None,
+            message: Some(format!("The condition on θ{} never held within t_max", params.link)),
+        },
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct EnergyVideoParams {
+    #[serde(flatten)]
+    sim: SimParams,
+    #[serde(default = "default_frame_stride")]
+    frame_stride: usize, // Keep every k-th sample as a frame
+    #[serde(default = "default_gif_size")]
+    size: u32,           // Edge of each (square) panel (px); the GIF is twice as wide
+}
+
+#[derive(Serialize)]
+struct EnergyVideoResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gif: Option<String>, // Base64 animated GIF
+    frames: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Energy Video Handler: Animates the chain next to a live E(t) plot in one GIF.
+pub async fn energy_video_handler(params: web::Json<EnergyVideoParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(EnergyVideoResponse {
+            success: false,
+            gif: None,
+            frames: 0,
+            message: Some(message),
+        }))
+    };
+
+    let prepared = match prepare_sim(&params.sim) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+    if params.frame_stride == 0 || !(100..=800).contains(&params.size) {
+        return fail("Need frame_stride >= 1 and size between 100 and 800".to_string());
+    }
+    let n_frames = params.sim.n_points.div_ceil(params.frame_stride);
+    if n_frames > MAX_GIF_FRAMES {
+        return fail(format!(
+            "Animation would have {} frames, the limit is {}; raise frame_stride",
+            n_frames, MAX_GIF_FRAMES
+        ));
+    }
+
+    let SimRun { solver, t, sol, limit, .. } = match run_prepared(&params.sim, prepared, |_, _| false) {
+        Ok(run) => run,
+        Err(e) => return fail(e.to_string()),
+    };
+    let energy: Vec<f64> = sol.iter().map(|state| solver.energy(state)).collect();
+    let frames: Vec<(usize, Vec<(f64, f64)>)> = (0..sol.len())
+        .step_by(params.frame_stride)
+        .map(|i| (i, solver.bob_positions(t[i], &sol[i])))
+        .collect();
+
+    // Same real-time playback as /fan
+    let dt = params.sim.t_max.abs() / (params.sim.n_points - 1) as f64;
+    let delay_ms = ((dt * params.frame_stride as f64 * 1000.0).round() as u32).max(20);
+    match plot::motion_energy_gif_base64(&frames, &t, &energy, limit, params.size, delay_ms) {
+        Ok(gif) => Ok(HttpResponse::Ok().json(EnergyVideoResponse {
+            success: true,
+            gif: Some(gif),
+            frames: n_frames,
+            message: None,
+        })),
+        Err(e) => fail(format!("GIF rendering failed: {}", e)),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PhaseVolumeParams {
+    #[serde(flatten)]
+    sim: SimParams,
+    #[serde(default = "default_phase_volume_eps")]
+    eps: f64, // Offset of the neighbouring runs (rad, rad/s)
+}
+
+fn default_phase_volume_eps() -> f64 {
+    1e-6
+}
+
+#[derive(Serialize)]
+struct PhaseVolumeResponse {
+    success: bool,
+    t: Vec<f64>,
+    volume: Vec<f64>,    // Phase-space volume relative to t = 0, 1 for an exact Hamiltonian flow
+    max_deviation: f64,  // max |volume - 1|
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Phase Volume Handler: Liouville check, tracking how the integrator stretches phase-space volume.
+pub async fn phase_volume_handler(params: web::Json<PhaseVolumeParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(PhaseVolumeResponse {
+            success: false,
+            t: Vec::new(),
+            volume: Vec::new(),
+            max_deviation: 0.0,
+            message: Some(message),
+        }))
+    };
+
+    let prepared = match prepare_sim(&params.sim) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+    if !(params.eps > 0.0 && params.eps < 1e-2) {
+        return fail("eps must be between 0 and 0.01".to_string());
+    }
+    // The tangent map needs 4n extra runs
+    let total_points = (4 * params.sim.n + 1) * params.sim.n_points;
+    if total_points > MAX_BATCH_POINTS {
+        return fail(format!("Request needs {} samples, the limit is {}", total_points, MAX_BATCH_POINTS));
+    }
+    if prepared.solver.masses[1..].iter().any(|&m| m.is_nan() || m <= 0.0) {
+        return fail("Phase-space volume needs every mass to be positive".to_string());
+    }
+
+    let (t, volume) = match prepared.solver.phase_volume(
+        &prepared.initial_angles,
+        &prepared.initial_ang_vels,
+        params.sim.t_max,
+        params.sim.n_points,
+        params.eps,
+    ) {
+        Ok(run) => run,
+        Err(e) => return fail(e.to_string()),
+    };
+    let max_deviation = volume.iter().map(|v| (v - 1.0).abs()).fold(0.0, f64::max);
+
+    Ok(HttpResponse::Ok().json(PhaseVolumeResponse {
+        success: true,
+        t,
+        volume,
+        max_deviation,
+        message: None,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct AutocorrelationParams {
+    #[serde(flatten)]
+    sim: SimParams,
+    #[serde(default = "default_link")]
+    link: usize,          // Link whose angle is analyzed, 1-based
+    #[serde(default)]
+    max_lag: Option<f64>, // Largest lag (s), default half the run
+}
+
+#[derive(Serialize)]
+struct AutocorrelationResponse {
+    success: bool,
+    lags: Vec<f64>, // τ (s)
+    r: Vec<f64>,    // Normalized autocorrelation ρ(τ), 1 at τ = 0
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plot_image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Autocorrelation Handler: ρ(τ) of θ_link, oscillating for periodic motion and decaying for chaos.
+pub async fn autocorrelation_handler(params: web::Json<AutocorrelationParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(AutocorrelationResponse {
+            success: false,
+            lags: Vec::new(),
+            r: Vec::new(),
+            plot_image: None,
+            message: Some(message),
+        }))
+    };
+
+    let prepared = match prepare_sim(&params.sim) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+    if !(1..=params.sim.n).contains(&params.link) {
+        return fail(format!("link must be between 1 and {}", params.sim.n));
+    }
+    let t_span = params.sim.t_max.abs();
+    let max_lag = params.max_lag.unwrap_or(0.5 * t_span);
+    if !(max_lag > 0.0 && max_lag < t_span) {
+        return fail(format!("max_lag must be positive and below the run length {} s, got {}", t_span, max_lag));
+    }
+
+    let SimRun { solver, t, sol, .. } = match run_prepared(&params.sim, prepared, |_, _| false) {
+        Ok(run) => run,
+        Err(e) => return fail(e.to_string()),
+    };
+    let dt = t_span / (params.sim.n_points - 1) as f64;
+    let lag_samples = (max_lag / dt).floor() as usize;
+    let series = match solver.autocorrelation(&t, &sol, params.link, lag_samples) {
+        Ok(series) => series,
+        Err(e) => return fail(e),
+    };
+
+    let (plot_image, message) = match plot::xy_plot_png_base64(
+        &format!("Autocorrelation of θ{}", params.link),
+        "τ (s)",
+        "ρ(τ)",
+        &[(format!("θ{}", params.link), series.clone())],
+    ) {
+        Ok(img) => (Some(img), None),
+        Err(e) => (None, Some(format!("Plot rendering failed: {}", e))),
+    };
+    let (lags, r) = series.into_iter().unzip();
+
+    Ok(HttpResponse::Ok().json(AutocorrelationResponse {
+        success: true,
+        lags,
+        r,
+        plot_image,
+        message,
+    }))
+}
+
+#[derive(Serialize)]
+struct MassEigenvaluesResponse {
+    success: bool,
+    t: Vec<f64>,
+    min_eigenvalues: Vec<f64>, // Smallest eigenvalue of M per sample (kg·m²)
+    max_eigenvalues: Vec<f64>, // Largest eigenvalue of M per sample (kg·m²)
+    max_condition: f64,        // Worst max/min ratio over the run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plot_image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Mass Eigenvalues Handler: Tracks the spread of M's eigenvalues to flag ill-conditioned poses.
+/// One eigendecomposition per sample, so it is a separate endpoint rather than part of /simulate.
+pub async fn mass_eigenvalues_handler(params: web::Json<SimParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(MassEigenvaluesResponse {
+            success: false,
+            t: Vec::new(),
+            min_eigenvalues: Vec::new(),
+            max_eigenvalues: Vec::new(),
+            max_condition: 0.0,
+            plot_image: None,
+            message: Some(message),
+        }))
+    };
+
+    let prepared = match prepare_sim(&params) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+    if prepared.solver.masses[1..].iter().any(|&m| m.is_nan() || m <= 0.0) {
+        return fail("Eigenvalues of M need every mass to be positive".to_string());
+    }
+
+    let SimRun { solver, t, sol, .. } = match run_prepared(&params, prepared, |_, _| false) {
+        Ok(run) => run,
+        Err(e) => return fail(e.to_string()),
+    };
+    let (min_eigenvalues, max_eigenvalues): (Vec<f64>, Vec<f64>) =
+        solver.mass_matrix_eigenvalue_range(&t, &sol).into_iter().unzip();
+    let max_condition = min_eigenvalues
+        .iter()
+        .zip(&max_eigenvalues)
+        .map(|(lo, hi)| hi / lo)
+        .fold(0.0, f64::max);
+
+    let series = |name: &str, values: &[f64]| (name.to_string(), t.iter().copied().zip(values.iter().copied()).collect());
+    let (plot_image, message) = match plot::xy_plot_png_base64(
+        "Mass matrix eigenvalues",
+        "t (s)",
+        "Eigenvalue (kg·m²)",
+        &[series("λ_min", &min_eigenvalues), series("λ_max", &max_eigenvalues)],
+    ) {
+        Ok(img) => (Some(img), None),
+        Err(e) => (None, Some(format!("Plot rendering failed: {}", e))),
+    };
+
+    Ok(HttpResponse::Ok().json(MassEigenvaluesResponse {
+        success: true,
+        t,
+        min_eigenvalues,
+        max_eigenvalues,
+        max_condition,
+        plot_image,
+        message,
+    }))
+}
+
+#[derive(Serialize)]
+struct SweptAreaResponse {
+    success: bool,
+    t: Vec<f64>,
+    areas: Vec<Vec<f64>>, // Cumulative swept area [A1, ..., An] per sample (m²), counterclockwise positive
+    total: Vec<f64>,      // Area per link at the end of the run (m²)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plot_image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Swept Area Handler: Area swept about the pivot by each bob over time, see `swept_area_series`.
+pub async fn swept_area_handler(params: web::Json<SimParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(SweptAreaResponse {
+            success: false,
+            t: Vec::new(),
+            areas: Vec::new(),
+            total: Vec::new(),
+            plot_image: None,
+            message: Some(message),
+        }))
+    };
+
+    let prepared = match prepare_sim(&params) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+
+    let SimRun { solver, t, sol, styles, .. } = match run_prepared(&params, prepared, |_, _| false) {
+        Ok(run) => run,
+        Err(e) => return fail(e.to_string()),
+    };
+    let areas = solver.swept_area_series(&t, &sol);
+    let total = areas.last().cloned().unwrap_or_default();
+
+    let series: Vec<(String, Vec<(f64, f64)>)> = styles
+        .iter()
+        .enumerate()
+        .map(|(k, style)| (style.label.clone(), t.iter().zip(&areas).map(|(&time, row)| (time, row[k])).collect()))
+        .collect();
+    let (plot_image, message) = match plot::xy_plot_png_base64("Swept area", "t (s)", "Area (m²)", &series) {
+        Ok(img) => (Some(img), None),
+        Err(e) => (None, Some(format!("Plot rendering failed: {}", e))),
+    };
+
+    Ok(HttpResponse::Ok().json(SweptAreaResponse {
+        success: true,
+        t,
+        areas,
+        total,
+        plot_image,
+        message,
+    }))
+}
+
+#[derive(Serialize)]
+struct SelfTestResponse {
+    passed: bool,
+    max_energy_drift: f64, // max |E(t) - E0| / |E0| of the frozen undamped triple pendulum
+    drift_limit: f64,      // Threshold the drift must stay under
+    max_state_error: f64,  // Largest deviation from the frozen reference states
+    elapsed_ms: f64,       // Wall time of the check
+}
+
+/// Self-Test Handler: Liveness and correctness probe for ops and CI. Runs `verify::check_triple_pendulum`
+/// (20 s of an undamped n = 3 chain) and answers 200 when it passes, 500 when the numerics regressed.
+pub async fn selftest_handler() -> Result<HttpResponse> {
+    let started = Instant::now();
+    let check = verify::check_triple_pendulum();
+    let response = SelfTestResponse {
+        passed: check.passed(),
+        max_energy_drift: check.max_energy_drift,
+        drift_limit: verify::TRIPLE_DRIFT_LIMIT,
+        max_state_error: check.max_state_error,
+        elapsed_ms: started.elapsed().as_secs_f64() * 1e3,
+    };
+    if response.passed {
+        Ok(HttpResponse::Ok().json(response))
+    } else {
+        log::error!("selftest failed: energy drift {:e}, state error {:e}", check.max_energy_drift, check.max_state_error);
+        Ok(HttpResponse::InternalServerError().json(response))
+    }
+}
+
+#[derive(Serialize)]
+struct AngularMomentumResponse {
+    success: bool,
+    t: Vec<f64>,
+    per_link: Vec<Vec<f64>>, // [L1, ..., Ln] about the pivot per sample (kg·m²/s)
+    total: Vec<f64>,         // Σ_k L_k per sample, conserved only without gravity, damping and forcing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plot_image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Angular Momentum Handler: Per-bob angular momentum about the pivot over time, plus the total.
+pub async fn angular_momentum_handler(params: web::Json<SimParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(AngularMomentumResponse {
+            success: false,
+            t: Vec::new(),
+            per_link: Vec::new(),
+            total: Vec::new(),
+            plot_image: None,
+            message: Some(message),
+        }))
+    };
+
+    let prepared = match prepare_sim(&params) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+
+    let SimRun { solver, t, sol, styles, .. } = match run_prepared(&params, prepared, |_, _| false) {
+        Ok(run) => run,
+        Err(e) => return fail(e.to_string()),
+    };
+    let per_link = solver.angular_momentum_series(&t, &sol);
+    let total: Vec<f64> = per_link.iter().map(|row| row.iter().sum()).collect();
+
+    let mut series: Vec<(String, Vec<(f64, f64)>)> = styles
+        .iter()
+        .enumerate()
+        .map(|(k, style)| (style.label.clone(), t.iter().zip(&per_link).map(|(&time, row)| (time, row[k])).collect()))
+        .collect();
+    series.push(("Total".to_string(), t.iter().copied().zip(total.iter().copied()).collect()));
+    let (plot_image, message) =
+        match plot::xy_plot_png_base64("Angular momentum about the pivot", "t (s)", "L (kg·m²/s)", &series) {
+            Ok(img) => (Some(img), None),
+            Err(e) => (None, Some(format!("Plot rendering failed: {}", e))),
+        };
+
+    Ok(HttpResponse::Ok().json(AngularMomentumResponse {
+        success: true,
+        t,
+        per_link,
+        total,
+        plot_image,
+        message,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct LandscapeParams {
+    #[serde(flatten)]
+    sim: SimParams,
+    #[serde(default = "default_link")]
+    link: usize, // Link whose angle is swept, 1-based; the others stay at their initial angles
+    #[serde(default = "default_landscape_samples")]
+    samples: usize,
+}
+
+fn default_landscape_samples() -> usize {
+    361
+}
+
+const MAX_LANDSCAPE_SAMPLES: usize = 10_000;
+
+#[derive(Serialize)]
+struct LandscapeResponse {
+    success: bool,
+    angles: Vec<f64>,           // θ_link over [-180, 180] (degrees)
+    potential_energy: Vec<f64>, // PE at each angle (J)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plot_image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// Potential Landscape Handler: PE along one link's angle with the rest of the chain frozen at its
+/// initial pose. No simulation is run.
+pub async fn potential_landscape_handler(params: web::Json<LandscapeParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(LandscapeResponse {
+            success: false,
+            angles: Vec::new(),
+            potential_energy: Vec::new(),
+            plot_image: None,
+            message: Some(message),
+        }))
+    };
+
+    let prepared = match prepare_sim(&params.sim) {
+        Ok(p) => p,
+        Err(e) => return fail(e.to_string()),
+    };
+    if !(1..=params.sim.n).contains(&params.link) {
+        return fail(format!("link must be between 1 and {}", params.sim.n));
+    }
+    if !(2..=MAX_LANDSCAPE_SAMPLES).contains(&params.samples) {
+        return fail(format!("samples must be between 2 and {}, got {}", MAX_LANDSCAPE_SAMPLES, params.samples));
+    }
+
+    let curve: Vec<(f64, f64)> = prepared
+        .solver
+        .potential_landscape(&prepared.initial_angles, params.link, params.samples)
+        .into_iter()
+        .map(|(theta, pe)| (theta.to_degrees(), pe))
+        .collect();
+    let (plot_image, message) = match plot::xy_plot_png_base64(
+        &format!("Potential energy along θ{}", params.link),
+        &format!("θ{} (°)", params.link),
+        "PE (J)",
+        &[("PE".to_string(), curve.clone())],
+    ) {
+        Ok(img) => (Some(img), None),
+        Err(e) => (None, Some(format!("Plot rendering failed: {}", e))),
+    };
+
+    let (angles, potential_energy) = curve.into_iter().unzip();
+    Ok(HttpResponse::Ok().json(LandscapeResponse {
+        success: true,
+        angles,
+        potential_energy,
+        plot_image,
+        message,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct FromPositionsParams {
+    #[serde(flatten)]
+    sim: SimParams,                 // initial_angles and angle_reference are replaced by the derived angles
+    bob_positions: Vec<(f64, f64)>, // Target [(x1, y1), ..., (xn, yn)] (m), y up with the pivot at the origin
+    #[serde(default = "default_position_tolerance")]
+    position_tolerance: f64,        // Allowed mismatch between each segment and its rod length (m)
+}
+
+fn default_position_tolerance() -> f64 {
+    1e-3
+}
+
+#[derive(Serialize)]
+struct FromPositionsResponse {
+    derived_angles: Vec<f64>, // Initial angles used (degrees from straight down), empty if the layout was rejected
+    #[serde(flatten)]
+    simulation: SimResponse,
+}
+
+/// From-Positions Handler: Starts the simulation from Cartesian bob positions instead of angles.
+/// The angles come from `angles_from_positions`; `initial_angles` may be left empty in the request.
+pub async fn from_positions_handler(params: web::Json<FromPositionsParams>) -> Result<HttpResponse> {
+    let fail = |message: String| {
+        Ok(HttpResponse::Ok().json(FromPositionsResponse {
+            derived_angles: Vec::new(),
+            simulation: sim_failure(message),
+        }))
+    };
+
+    let FromPositionsParams { mut sim, bob_positions, position_tolerance } = params.into_inner();
+    if !(position_tolerance.is_finite() && position_tolerance > 0.0) {
+        return fail(format!("position_tolerance must be positive and finite, got {}", position_tolerance));
+    }
+    let lengths = broadcast(parse_csv_f64(&sim.lengths), sim.n);
+    if lengths.len() != sim.n || bob_positions.len() != sim.n {
+        return fail(format!(
+            "Expected {} lengths and {} bob positions, got {} and {}",
+            sim.n,
+            sim.n,
+            lengths.len(),
+            bob_positions.len()
+        ));
+    }
+    let angles = match angles_from_positions(&one_based(&lengths), &bob_positions, position_tolerance) {
+        Ok(angles) => angles,
+        Err(e) => return fail(e),
+    };
+
+    // Degrees print with round-trip precision, so the solver gets these angles back up to rounding
+    let derived_angles: Vec<f64> = angles[1..].iter().map(|a| a.to_degrees()).collect();
+    sim.initial_angles = derived_angles.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(",");
+    sim.angle_reference = AngleReference::Down;
+
+    let simulation = simulate(&sim);
+    Ok(HttpResponse::Ok().json(FromPositionsResponse {
+        derived_angles,
+        simulation,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_results_keep_input_order() {
+        // 24 double pendulums, member i released at θ1 = i degrees with a length that also grows with i
+        // so the members' runs take different times, four times over. Exercises the parallel path
+        // when built with the `rayon` feature.
+        let members = 24;
+        let batch: Vec<SimParams> = (0..members)
+            .map(|i| {
+                serde_json::from_value(serde_json::json!({
+                    "n": 2,
+                    "masses": "1",
+                    "lengths": format!("{},1", 1.0 + 0.1 * i as f64),
+                    "initial_angles": format!("{},0", i),
+                    "t_max": 5.0,
+                    "n_points": 500 + 200 * (i % 5),
+                }))
+                .expect("valid batch member")
+            })
+            .collect();
+        let run = || map_in_order(&batch, |params| run_simulation(params).map(|run| run.sol).expect("batch member runs"));
+
+        let first = run();
+        assert_eq!(first.len(), members);
+        for (i, sol) in first.iter().enumerate() {
+            assert!((sol[0][0] - (i as f64).to_radians()).abs() < 1e-15, "member {} starts at {}", i, sol[0][0]);
+        }
+        for _ in 1..4 {
+            assert!(run() == first, "a repeat differs from the first run");
+        }
+    }
+
+    #[test]
+    fn gravity_override_lets_a_straight_chain_spin_rigidly() {
+        // A 3-link chain (1, 2, 1 kg; 1, 0.5, 1.5 m) held straight at 30° and spun at 90°/s on every link
+        // for 10 s. Without gravity nothing bends it, so it turns rigidly at constant speed (errors
+        // about 1e-9); under the default 9.81 gravity folds it.
+        let omega0 = 90f64.to_radians();
+        let run = |gravity: Option<f64>| {
+            let mut request = serde_json::json!({
+                "n": 3,
+                "masses": "1,2,1",
+                "lengths": "1,0.5,1.5",
+                "initial_angles": "30,30,30",
+                "initial_velocities": "90,90,90",
+                "t_max": 10.0,
+                "n_points": 2001,
+            });
+            if let Some(g) = gravity {
+                request["gravity"] = serde_json::json!(g);
+            }
+            let params: SimParams = serde_json::from_value(request).expect("valid zero-gravity request");
+            run_simulation(&params).expect("zero-gravity request runs").sol
+        };
+        let bend = |sol: &[DVector<f64>]| sol.iter().map(|y| (1..3).map(|k| (y[k] - y[0]).abs()).fold(0.0, f64::max)).fold(0.0, f64::max);
+
+        let free = run(Some(1e-9));
+        assert!(bend(&free) < 1e-8, "bent by {:e} rad", bend(&free));
+        let spin_drift = free.iter().map(|y| (3..6).map(|k| (y[k] - omega0).abs()).fold(0.0, f64::max)).fold(0.0, f64::max);
+        assert!(spin_drift < 1e-8, "spin drifted by {:e} rad/s", spin_drift);
+        assert!(bend(&run(None)) > 0.1);
+    }
+
+    #[test]
+    fn damped_energy_never_increases() {
+        // A unit double pendulum released at (60°, 30°) with damping "0.3,0.1" for 20 s, sampled every 0.01 s.
+        // Energy falls at every sample (the largest step is about -2e-6 J) and the swing shrinks from 60°
+        // to about 21°.
+        let params: SimParams = serde_json::from_value(serde_json::json!({
+            "n": 2,
+            "masses": "1",
+            "lengths": "1",
+            "initial_angles": "60,30",
+            "damping": "0.3,0.1",
+            "t_max": 20.0,
+            "n_points": 2001,
+        }))
+        .expect("valid damped request");
+        let run = run_simulation(&params).expect("damped request runs");
+        let energies: Vec<f64> = run.sol.iter().map(|y| run.solver.energy(y)).collect();
+        for (i, w) in energies.windows(2).enumerate() {
+            assert!(w[1] < w[0], "energy rose by {:e} J at sample {}", w[1] - w[0], i + 1);
+        }
+
+        let amplitude = |states: &[DVector<f64>]| states.iter().map(|y| y[0].abs()).fold(0.0, f64::max);
+        let (initial, last) = (amplitude(&run.sol[..200]), amplitude(&run.sol[run.sol.len() - 200..]));
+        assert!(last < 0.5 * initial, "amplitude {} rad after {} rad", last, initial);
+    }
+}
@@ -0,0 +1,218 @@
+// src/verify.rs
+use crate::logic::NPendulumSolver;
+use crate::math::STANDARD_GRAVITY;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Frozen reference states [θ1, θ2, θ3, ω1, ω2, ω3] for the triple pendulum below at
+/// t = 0.1, 0.2, 0.3, 0.4, 0.5 s, generated once with RK4 at dt = 1e-6.
+const TRIPLE_REFERENCE: [(f64, [f64; 6]); 5] = [
+    (0.1, [1.004527464262748, -0.4918485173537042, 1.570617859076201, -0.8577123750997353, 0.6570930091744819, -0.007430414670953501]),
+    (0.2, [0.872988982901952, -0.3827685602645944, 1.567150513389618, -1.802646770404712, 1.602647500641005, -0.08492208862256052]),
+    (0.3, [0.6301170408325188, -0.1437077499741048, 1.541960471003499, -3.19562168356196, 3.424364031297261, -0.5575556586222318]),
+    (0.4, [0.2013257668407941, 0.3708056599951389, 1.361101808080539, -4.906277901136072, 6.475009962647958, -3.970852823390245]),
+    (0.5, [-0.1349131583334597, 0.8674769222980382, 0.827755578259669, -1.875998631078567, 3.317230626160951, -5.796813304314167]),
+];
+
+/// Largest relative energy drift `check_triple_pendulum` accepts.
+pub const TRIPLE_DRIFT_LIMIT: f64 = 1e-6;
+
+/// Outcome of the n = 3 regression run.
+#[derive(Debug, Clone, Copy)]
+pub struct TripleCheck {
+    pub max_state_error: f64,  // Largest |state - reference| over the frozen samples
+    pub max_energy_drift: f64, // max |E(t) - E0| / |E0| over 20 s at dt = 1e-3
+}
+
+impl TripleCheck {
+    /// RK4 at dt = 1e-3 reproduces the references to ~3e-9 and holds energy to ~5e-9.
+    pub fn passed(&self) -> bool {
+        self.max_state_error < 1e-7 && self.max_energy_drift < TRIPLE_DRIFT_LIMIT
+    }
+}
+
+/// Regression anchor for the full matrix machinery at n = 3:
+/// m = [1, 1.5, 0.8] kg, L = [1, 0.8, 1.2] m, θ0 = [60°, -30°, 90°], released from rest.
+pub fn check_triple_pendulum() -> TripleCheck {
+    let solver = NPendulumSolver::new(3, vec![0.0, 1.0, 1.5, 0.8], vec![0.0, 1.0, 0.8, 1.2], STANDARD_GRAVITY);
+    let angles: Vec<f64> = [0.0, 60.0, -30.0, 90.0_f64].iter().map(|d| d.to_radians()).collect();
+    let ang_vels = vec![0.0; 4];
+
+    // dt = 1e-3, so reference time t sits at index t / dt
+    let (_t, sol) = solver.solve(angles.clone(), ang_vels.clone(), 0.5, 501).expect("test chain is nonsingular");
+    let max_state_error = TRIPLE_REFERENCE
+        .iter()
+        .map(|(t, reference)| {
+            let state = &sol[(t * 1000.0).round() as usize];
+            state.iter().zip(reference).map(|(a, b)| (a - b).abs()).fold(0.0, f64::max)
+        })
+        .fold(0.0, f64::max);
+
+    let (_t, sol) = solver.solve(angles, ang_vels, 20.0, 20_001).expect("test chain is nonsingular");
+    let e0 = solver.energy(&sol[0]);
+    let max_energy_drift = sol
+        .iter()
+        .map(|state| ((solver.energy(state) - e0) / e0).abs())
+        .fold(0.0, f64::max);
+
+    TripleCheck {
+        max_state_error,
+        max_energy_drift,
+    }
+}
+
+/// Fixed configuration whose trajectory is pinned by a golden file, see `check_goldens`.
+pub struct GoldenCase {
+    name: &'static str,
+    masses: &'static [f64],  // 1-based padded, like the solver
+    lengths: &'static [f64],
+    angles_deg: &'static [f64],
+    ang_vels: &'static [f64],
+    t_max: f64,
+    n_points: usize,
+}
+
+/// n = 1, 2 and 3 at large amplitudes, short enough that round-off from reordered arithmetic
+/// stays far below `GOLDEN_TOLERANCE` despite the chaotic growth of the n > 1 cases.
+pub const GOLDEN_CASES: [GoldenCase; 3] = [
+    GoldenCase {
+        name: "single",
+        masses: &[0.0, 1.0],
+        lengths: &[0.0, 1.0],
+        angles_deg: &[0.0, 120.0],
+        ang_vels: &[0.0, 0.0],
+        t_max: 3.0,
+        n_points: 301,
+    },
+    GoldenCase {
+        name: "double",
+        masses: &[0.0, 1.0, 2.0],
+        lengths: &[0.0, 1.0, 0.5],
+        angles_deg: &[0.0, 90.0, -45.0],
+        ang_vels: &[0.0, 0.0, 1.0],
+        t_max: 2.0,
+        n_points: 201,
+    },
+    GoldenCase {
+        name: "triple",
+        masses: &[0.0, 1.0, 1.5, 0.8],
+        lengths: &[0.0, 1.0, 0.8, 1.2],
+        angles_deg: &[0.0, 60.0, -30.0, 90.0],
+        ang_vels: &[0.0, 0.0, 0.0, 0.0],
+        t_max: 2.0,
+        n_points: 201,
+    },
+];
+
+/// Largest allowed |state - golden| entry; refactors meant to give identical output stay near 1e-14.
+pub const GOLDEN_TOLERANCE: f64 = 1e-10;
+
+/// Contents of one golden file: the configuration for reference plus the recorded run.
+#[derive(Serialize, Deserialize)]
+struct Golden {
+    n: usize,
+    masses: Vec<f64>,
+    lengths: Vec<f64>,
+    angles_deg: Vec<f64>,
+    ang_vels: Vec<f64>,
+    t: Vec<f64>,
+    states: Vec<Vec<f64>>, // [θ1..θn, ω1..ωn] per sample
+}
+
+impl GoldenCase {
+    /// Runs the case with the default RK4 solver.
+    fn run(&self) -> Golden {
+        let n = self.masses.len() - 1;
+        let solver = NPendulumSolver::new(n, self.masses.to_vec(), self.lengths.to_vec(), STANDARD_GRAVITY);
+        let angles = self.angles_deg.iter().map(|d| d.to_radians()).collect();
+        let (t, sol) = solver.solve(angles, self.ang_vels.to_vec(), self.t_max, self.n_points).expect("test chain is nonsingular");
+        Golden {
+            n,
+            masses: self.masses.to_vec(),
+            lengths: self.lengths.to_vec(),
+            angles_deg: self.angles_deg.to_vec(),
+            ang_vels: self.ang_vels.to_vec(),
+            t,
+            states: sol.iter().map(|state| state.iter().copied().collect()).collect(),
+        }
+    }
+
+    fn path(&self, dir: &Path) -> PathBuf {
+        dir.join(format!("{}.json", self.name))
+    }
+}
+
+/// Outcome of comparing one case against its golden file.
+#[derive(Debug, Clone)]
+pub struct GoldenCheck {
+    pub name: &'static str,
+    pub max_error: f64, // Largest |state - golden| entry, infinite if the shapes differ
+}
+
+impl GoldenCheck {
+    pub fn passed(&self) -> bool {
+        self.max_error <= GOLDEN_TOLERANCE
+    }
+}
+
+/// Rewrites every golden file in `dir` from the current code. Only for intentional changes
+/// of the numerics; the diff of the files shows what moved.
+pub fn write_goldens(dir: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create '{}': {}", dir.display(), e))?;
+    for case in &GOLDEN_CASES {
+        let path = case.path(dir);
+        let json = serde_json::to_string_pretty(&case.run()).map_err(|e| e.to_string())?;
+        std::fs::write(&path, json + "\n").map_err(|e| format!("Cannot write '{}': {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Re-runs every case and compares it with its golden file in `dir`.
+/// Fails only if a file is missing or unreadable; mismatches are reported per case.
+pub fn check_goldens(dir: &Path) -> Result<Vec<GoldenCheck>, String> {
+    GOLDEN_CASES
+        .iter()
+        .map(|case| {
+            let path = case.path(dir);
+            let text = std::fs::read_to_string(&path).map_err(|e| format!("Cannot read '{}': {}", path.display(), e))?;
+            let golden: Golden =
+                serde_json::from_str(&text).map_err(|e| format!("Invalid golden '{}': {}", path.display(), e))?;
+            let run = case.run();
+
+            let same_shape = golden.t.len() == run.t.len()
+                && golden.states.iter().zip(&run.states).all(|(a, b)| a.len() == b.len());
+            let max_error = if same_shape {
+                let t_error = golden.t.iter().zip(&run.t).map(|(a, b)| (a - b).abs());
+                let state_error = golden
+                    .states
+                    .iter()
+                    .zip(&run.states)
+                    .flat_map(|(a, b)| a.iter().zip(b).map(|(x, y)| (x - y).abs()));
+                t_error.chain(state_error).fold(0.0, f64::max)
+            } else {
+                f64::INFINITY
+            };
+            Ok(GoldenCheck { name: case.name, max_error })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn triple_pendulum_regression() {
+        let check = check_triple_pendulum();
+        assert!(check.passed(), "{:?}", check);
+    }
+
+    #[test]
+    fn goldens() {
+        let checks = check_goldens(&Path::new(env!("CARGO_MANIFEST_DIR")).join("goldens")).expect("golden files are readable");
+        assert_eq!(checks.len(), GOLDEN_CASES.len());
+        for check in checks {
+            assert!(check.passed(), "{:?}", check);
+        }
+    }
+}